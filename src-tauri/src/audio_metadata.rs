@@ -0,0 +1,203 @@
+use crate::media_cache;
+use crate::media_info::MediaInfo;
+use crate::media_toolkit::{CliToolkit, MediaToolkit};
+use crate::probe_cache::CachedToolkit;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// Subdirectory of the cache directory holding extracted cover images.
+const COVER_ART_DIR_NAME: &str = "covers";
+
+// Least recently used covers are deleted beyond this. Embedded covers are
+// usually 50-500 KB, with the odd multi-megabyte scan.
+const MAX_COVER_ART_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AudioMetadata {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    album_artist: Option<String>,
+    genre: Option<String>,
+    track_number: Option<u32>,
+    track_total: Option<u32>,
+    disc_number: Option<u32>,
+    disc_total: Option<u32>,
+    year: Option<i32>,
+    duration: Option<f64>,
+    // Extracted image in the cache directory, None if the file has no cover.
+    cover_art_path: Option<String>,
+}
+
+// ID3, Vorbis comment and MP4 tag names as ffprobe reports them, lower-cased.
+// Container tags take precedence; Ogg and Opus only have stream tags.
+fn collect_tags(info: &MediaInfo) -> HashMap<String, String> {
+    let mut tags = HashMap::new();
+    let stream_tags = info.audio_streams.first().map(|stream| &stream.tags);
+    for source in stream_tags.into_iter().chain([&info.tags]) {
+        for (key, value) in source {
+            let value = value.trim();
+            if !value.is_empty() {
+                tags.insert(key.to_lowercase(), value.to_string());
+            }
+        }
+    }
+    tags
+}
+
+fn first_tag(tags: &HashMap<String, String>, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| tags.get(*key).cloned())
+}
+
+// Track and disc numbers come as "3" or "3/12".
+fn parse_position(value: Option<String>) -> (Option<u32>, Option<u32>) {
+    let Some(value) = value else {
+        return (None, None);
+    };
+    match value.split_once('/') {
+        Some((number, total)) => (number.trim().parse().ok(), total.trim().parse().ok()),
+        None => (value.trim().parse().ok(), None),
+    }
+}
+
+// Dates range from "2003" to "2003-05-01T00:00:00Z"; only the year is kept.
+fn parse_year(value: Option<String>) -> Option<i32> {
+    value?.trim().get(..4)?.parse().ok()
+}
+
+fn metadata_from_tags(info: &MediaInfo) -> AudioMetadata {
+    let tags = collect_tags(info);
+
+    let (track_number, track_total) = parse_position(first_tag(&tags, &["track", "tracknumber"]));
+    let (disc_number, disc_total) = parse_position(first_tag(&tags, &["disc", "discnumber"]));
+
+    AudioMetadata {
+        title: first_tag(&tags, &["title"]),
+        artist: first_tag(&tags, &["artist", "performer"]),
+        album: first_tag(&tags, &["album"]),
+        album_artist: first_tag(&tags, &["album_artist", "albumartist", "album artist"]),
+        genre: first_tag(&tags, &["genre"]),
+        track_number,
+        track_total: track_total.or_else(|| {
+            first_tag(&tags, &["tracktotal", "totaltracks"]).and_then(|t| t.parse().ok())
+        }),
+        disc_number,
+        disc_total: disc_total.or_else(|| {
+            first_tag(&tags, &["disctotal", "totaldiscs"]).and_then(|t| t.parse().ok())
+        }),
+        year: parse_year(first_tag(&tags, &["date", "year", "originaldate"])),
+        duration: info.duration,
+        cover_art_path: None,
+    }
+}
+
+// Cover file name derived from the audio file's identity, so an edited or
+// replaced file gets a fresh cover while unchanged files reuse theirs.
+fn cover_art_path(audio_path: &str, extension: &str) -> Result<PathBuf, String> {
+    let digest = media_cache::file_digest(audio_path, "")?;
+    Ok(media_cache::dir(COVER_ART_DIR_NAME)?.join(format!("{}.{}", digest, extension)))
+}
+
+async fn extract_cover_art<T: MediaToolkit>(
+    toolkit: &T,
+    audio_path: &str,
+    info: &MediaInfo,
+) -> Result<Option<String>, String> {
+    let Some(picture) = info.attachments.iter().find(|a| a.is_attached_pic) else {
+        return Ok(None);
+    };
+
+    // JPEG covers are copied byte for byte; anything else becomes PNG.
+    let extension = match picture.mime_type.as_deref() {
+        Some("image/jpeg") => "jpg",
+        _ => "png",
+    };
+    let target = cover_art_path(audio_path, extension)?;
+    media_cache::get_or_create(&target, MAX_COVER_ART_CACHE_BYTES, |partial| async move {
+        toolkit
+            .extract_cover_art(audio_path, picture.index, &partial)
+            .await
+    })
+    .await?;
+
+    Ok(Some(target.to_string_lossy().to_string()))
+}
+
+pub(crate) async fn audio_metadata<T: MediaToolkit>(
+    toolkit: &T,
+    audio_path: &str,
+) -> Result<AudioMetadata, String> {
+    let info = toolkit.probe_media(audio_path).await?;
+    let mut metadata = metadata_from_tags(&info);
+
+    // A broken cover should not hide the rest of the track information.
+    metadata.cover_art_path = extract_cover_art(toolkit, audio_path, &info)
+        .await
+        .inspect_err(|_e| {
+            #[cfg(debug_assertions)]
+            eprintln!(
+                "[Audio Metadata] Failed to extract cover art for {}: {}",
+                audio_path, _e
+            );
+        })
+        .ok()
+        .flatten();
+
+    Ok(metadata)
+}
+
+// Title, artist, album, numbering and cover art for the audio player and
+// gallery.
+#[tauri::command]
+pub(crate) async fn get_audio_metadata(audio_path: String) -> Result<AudioMetadata, String> {
+    audio_metadata(&CachedToolkit::new(CliToolkit), &audio_path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media_info::AudioStreamInfo;
+    use std::collections::BTreeMap;
+
+    fn tags(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn container_tags_win_over_stream_tags() {
+        let info = MediaInfo {
+            tags: tags(&[
+                ("TITLE", "Container"),
+                ("track", "3/12"),
+                ("date", "2003-05-01"),
+            ]),
+            audio_streams: vec![AudioStreamInfo {
+                tags: tags(&[("title", "Stream"), ("ARTIST", "Band"), ("DISCTOTAL", "2")]),
+                ..AudioStreamInfo::default()
+            }],
+            ..MediaInfo::default()
+        };
+        let metadata = metadata_from_tags(&info);
+
+        assert_eq!(metadata.title.as_deref(), Some("Container"));
+        assert_eq!(metadata.artist.as_deref(), Some("Band"));
+        assert_eq!(
+            (metadata.track_number, metadata.track_total),
+            (Some(3), Some(12))
+        );
+        assert_eq!(metadata.disc_total, Some(2));
+        assert_eq!(metadata.year, Some(2003));
+    }
+
+    #[test]
+    fn malformed_positions_and_dates_are_dropped() {
+        assert_eq!(parse_position(Some("x/y".to_string())), (None, None));
+        assert_eq!(parse_position(Some(" 7 ".to_string())), (Some(7), None));
+        assert_eq!(parse_year(Some("03".to_string())), None);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::settings::{self, Settings};
+use crate::WatchProgress;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+// Version of the backup bundle layout. Bump when a field is renamed or
+// retyped; older bundles must keep importing.
+const BACKUP_FORMAT_VERSION: u32 = 1;
+
+// A complete snapshot of user data in one JSON file. The PiP layout travels
+// inside `settings` alongside the rest of config.json, which is kept as raw
+// JSON so older bundles are migrated exactly like an older config.json.
+#[derive(Serialize, Deserialize)]
+struct UserDataBundle {
+    format_version: u32,
+    app_version: String,
+    exported_at: u64,
+    settings: serde_json::Value,
+    watch_progress: HashMap<String, WatchProgress>,
+    // Informational only: model files are far too large to bundle, so import
+    // reports which ones still need downloading on the new machine.
+    #[serde(default)]
+    whisper_models: Vec<String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ImportMode {
+    // Discard local watch history and use the bundle's.
+    Replace,
+    // Keep whichever entry was watched most recently for each path.
+    Merge,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct ImportSummary {
+    progress_entries: usize,
+    missing_models: Vec<String>,
+    // Settings from the bundle that were not applied because they belong to
+    // the machine it came from: setting names, and library folders that do not
+    // exist here.
+    skipped_settings: Vec<String>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn merge_watch_progress(
+    local: &mut HashMap<String, WatchProgress>,
+    imported: HashMap<String, WatchProgress>,
+) {
+    for (path, entry) in imported {
+        match local.get(&path) {
+            Some(existing) if existing.last_watched >= entry.last_watched => {}
+            _ => {
+                local.insert(path, entry);
+            }
+        }
+    }
+}
+
+// Applies `imported` over `current` except for settings tied to the machine
+// the bundle came from. Executable paths and the download mirror always stay
+// local; library folders are kept only if they exist here. Returns what was
+// skipped.
+fn machine_independent_settings(
+    mut imported: Settings,
+    current: &Settings,
+) -> (Settings, Vec<String>) {
+    let mut skipped = Vec::new();
+    let local_only = [
+        (
+            "ffmpeg_custom_path",
+            &mut imported.ffmpeg_custom_path,
+            &current.ffmpeg_custom_path,
+        ),
+        (
+            "ffprobe_custom_path",
+            &mut imported.ffprobe_custom_path,
+            &current.ffprobe_custom_path,
+        ),
+        (
+            "managed_ffmpeg_base_url",
+            &mut imported.managed_ffmpeg_base_url,
+            &current.managed_ffmpeg_base_url,
+        ),
+    ];
+    for (key, imported_value, current_value) in local_only {
+        if imported_value.is_some() && imported_value != current_value {
+            skipped.push(key.to_string());
+        }
+        *imported_value = current_value.clone();
+    }
+
+    if let Some(paths) = imported.gallery_paths.take() {
+        let (present, missing): (Vec<String>, Vec<String>) =
+            paths.into_iter().partition(|path| Path::new(path).is_dir());
+        // A library made up entirely of folders from the other machine would
+        // leave the gallery empty, so the local one is kept instead.
+        imported.gallery_paths = if present.is_empty() && !missing.is_empty() {
+            current.gallery_paths.clone()
+        } else {
+            Some(present)
+        };
+        skipped.extend(missing);
+    }
+
+    (imported, skipped)
+}
+
+#[tauri::command]
+pub(crate) fn export_user_data(path: String) -> Result<(), String> {
+    let settings = serde_json::to_value(settings::load_settings()?)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    let watch_progress = {
+        let progress_file = crate::watch_progress_path()?;
+        let _guard = crate::watch_progress_lock()
+            .lock()
+            .map_err(|_| "Watch progress lock poisoned".to_string())?;
+        crate::read_watch_progress_shared(&progress_file)?
+    };
+
+    let bundle = UserDataBundle {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: now_secs(),
+        settings,
+        watch_progress,
+        whisper_models: crate::check_installed_models()?,
+    };
+
+    let content = serde_json::to_string_pretty(&bundle)
+        .map_err(|e| format!("Failed to serialize backup: {}", e))?;
+    // Written next to the target under a unique name and renamed into place,
+    // so a failed export never leaves a truncated backup behind.
+    crate::recovery::replace_file(Path::new(&path), content.as_bytes())
+        .map_err(|e| format!("Failed to write backup file: {}", e))?;
+
+    #[cfg(debug_assertions)]
+    println!("Exported user data to: {}", path);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn import_user_data(path: String, mode: ImportMode) -> Result<ImportSummary, String> {
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read backup file: {}", e))?;
+    let bundle: UserDataBundle = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse backup file: {}", e))?;
+
+    if bundle.format_version > BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "This backup was created by a newer version of Glucose ({}). Please update to import it.",
+            bundle.app_version
+        ));
+    }
+
+    // Everything in the bundle is validated before anything is written.
+    let imported_settings = settings::settings_from_value(bundle.settings)?;
+    let mut skipped_settings = Vec::new();
+
+    let progress_entries = {
+        let progress_file = crate::watch_progress_path()?;
+        let _guard = crate::watch_progress_lock()
+            .lock()
+            .map_err(|_| "Watch progress lock poisoned".to_string())?;
+        let _file_lock = crate::acquire_watch_progress_file_lock_exclusive(&progress_file)?;
+
+        let local = crate::read_watch_progress(&progress_file)?;
+        let progress_map = match mode {
+            ImportMode::Replace => bundle.watch_progress,
+            ImportMode::Merge => {
+                let mut merged = local.clone();
+                merge_watch_progress(&mut merged, bundle.watch_progress);
+                merged
+            }
+        };
+
+        // Watch history first, settings second. If the settings write fails
+        // the previous history is put back, so an import either lands in full
+        // or leaves both files as they were.
+        crate::write_watch_progress(&progress_file, &progress_map)?;
+        let saved = settings::modify_settings(|current| {
+            let (merged, skipped) = machine_independent_settings(imported_settings, current);
+            *current = merged;
+            skipped_settings = skipped;
+            Ok(())
+        });
+        if let Err(e) = saved {
+            let _ = crate::write_watch_progress(&progress_file, &local);
+            return Err(e);
+        }
+        progress_map.len()
+    };
+
+    let installed = crate::check_installed_models()?;
+    let missing_models = bundle
+        .whisper_models
+        .into_iter()
+        .filter(|model| !installed.contains(model))
+        .collect();
+
+    #[cfg(debug_assertions)]
+    println!("Imported user data from: {}", path);
+
+    Ok(ImportSummary {
+        progress_entries,
+        missing_models,
+        skipped_settings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(last_watched: u64) -> WatchProgress {
+        serde_json::from_value(serde_json::json!({
+            "path": "/v.mkv",
+            "current_time": 1.0,
+            "duration": 2.0,
+            "last_watched": last_watched,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merge_keeps_most_recently_watched_entry() {
+        let mut local = HashMap::from([
+            ("/a".to_string(), progress(10)),
+            ("/b".to_string(), progress(30)),
+        ]);
+        let imported = HashMap::from([
+            ("/a".to_string(), progress(20)),
+            ("/b".to_string(), progress(5)),
+            ("/c".to_string(), progress(1)),
+        ]);
+        merge_watch_progress(&mut local, imported);
+
+        assert_eq!(local.len(), 3);
+        assert_eq!(local["/a"].last_watched, 20);
+        assert_eq!(local["/b"].last_watched, 30);
+    }
+
+    #[test]
+    fn machine_specific_settings_stay_local() {
+        let current = Settings {
+            ffmpeg_custom_path: Some("/usr/local/bin/ffmpeg".to_string()),
+            gallery_paths: Some(vec!["/local/videos".to_string()]),
+            ..Settings::default()
+        };
+        let here = std::env::temp_dir().to_string_lossy().to_string();
+        let imported = Settings {
+            setup_completed: true,
+            ffmpeg_custom_path: Some("C:\\ffmpeg\\ffmpeg.exe".to_string()),
+            managed_ffmpeg_base_url: Some("https://mirror.example".to_string()),
+            gallery_paths: Some(vec![here.clone(), "/not/on/this/machine".to_string()]),
+            ..Settings::default()
+        };
+
+        let (merged, skipped) = machine_independent_settings(imported, &current);
+
+        assert!(merged.setup_completed);
+        assert_eq!(merged.ffmpeg_custom_path, current.ffmpeg_custom_path);
+        assert!(merged.managed_ffmpeg_base_url.is_none());
+        assert_eq!(merged.gallery_paths, Some(vec![here]));
+        assert_eq!(
+            skipped,
+            [
+                "ffmpeg_custom_path",
+                "managed_ffmpeg_base_url",
+                "/not/on/this/machine"
+            ]
+        );
+    }
+
+    #[test]
+    fn library_of_only_missing_folders_keeps_local_one() {
+        let current = Settings {
+            gallery_paths: Some(vec!["/local/videos".to_string()]),
+            ..Settings::default()
+        };
+        let imported = Settings {
+            gallery_paths: Some(vec!["/not/on/this/machine".to_string()]),
+            ..Settings::default()
+        };
+
+        let (merged, _) = machine_independent_settings(imported, &current);
+        assert_eq!(merged.gallery_paths, current.gallery_paths);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use crate::process_scheduler::{self, Priority};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+// Detected once per resolved ffmpeg binary. Cleared by
+// ffmpeg::invalidate_ffmpeg_cache and re-detected if the resolved path changes.
+static CAPABILITIES_CACHE: Mutex<Option<FfmpegCapabilities>> = Mutex::new(None);
+
+#[derive(Clone, Copy)]
+pub(crate) enum Component {
+    Encoder,
+    Muxer,
+    Filter,
+}
+
+impl Component {
+    fn label(self) -> &'static str {
+        match self {
+            Component::Encoder => "encoder",
+            Component::Muxer => "muxer",
+            Component::Filter => "filter",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Default)]
+pub(crate) struct FfmpegCapabilities {
+    ffmpeg_path: Option<String>,
+    encoders: BTreeSet<String>,
+    decoders: BTreeSet<String>,
+    muxers: BTreeSet<String>,
+    filters: BTreeSet<String>,
+}
+
+impl FfmpegCapabilities {
+    pub(crate) fn has(&self, component: Component, name: &str) -> bool {
+        let set = match component {
+            Component::Encoder => &self.encoders,
+            Component::Muxer => &self.muxers,
+            Component::Filter => &self.filters,
+        };
+        set.contains(name)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_encoders(names: &[&str]) -> Self {
+        FfmpegCapabilities {
+            encoders: names.iter().map(|name| name.to_string()).collect(),
+            ..FfmpegCapabilities::default()
+        }
+    }
+}
+
+// Flag columns are upper-case letters, dots and the lower-case `d` device
+// marker; entry names are always lower-case, so the first token with any
+// other character is the name.
+fn is_flag_column(token: &str) -> bool {
+    token
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c == '.' || c == 'd')
+}
+
+// Parses `-encoders`, `-decoders` and `-muxers` listings: a legend, a dashed
+// separator line, then one `<flags> <name>[,<alias>...] <description>` row per
+// entry.
+fn parse_codec_listing(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .skip_while(|line| {
+            let trimmed = line.trim();
+            trimmed.is_empty() || !trimmed.chars().all(|c| c == '-')
+        })
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().find(|t| !is_flag_column(t)))
+        .flat_map(|names| names.split(','))
+        .map(str::to_string)
+        .collect()
+}
+
+// Parses `-filters`, which has no separator; rows look like
+// ` TSC amix  N->A  Audio mixing.` so the third column gives them away.
+fn parse_filter_listing(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let _flags = columns.next()?;
+            let name = columns.next()?;
+            columns
+                .next()
+                .filter(|io| io.contains("->"))
+                .map(|_| name.to_string())
+        })
+        .collect()
+}
+
+fn run_listing(flag: &str) -> Result<String, String> {
+    // Built first: resolving the path can run probes of its own.
+    let mut cmd = crate::get_ffmpeg_command();
+    let _permit = process_scheduler::acquire_blocking(Priority::Interactive)?;
+    let output = cmd
+        .args(["-hide_banner", flag])
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg {}: {}", flag, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg {} failed: {}",
+            flag,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn detect_capabilities(ffmpeg_path: Option<String>) -> Result<FfmpegCapabilities, String> {
+    if ffmpeg_path.is_none() {
+        return Err("FFmpeg not found".to_string());
+    }
+
+    Ok(FfmpegCapabilities {
+        ffmpeg_path,
+        encoders: parse_codec_listing(&run_listing("-encoders")?),
+        decoders: parse_codec_listing(&run_listing("-decoders")?),
+        muxers: parse_codec_listing(&run_listing("-muxers")?),
+        filters: parse_filter_listing(&run_listing("-filters")?),
+    })
+}
+
+pub(crate) fn invalidate_capabilities_cache() {
+    *CAPABILITIES_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+// Returns the capabilities of the currently resolved ffmpeg, detecting them
+// on first use. Blocking: spawns ffmpeg four times on a cache miss.
+pub(crate) fn get_capabilities() -> Result<FfmpegCapabilities, String> {
+    let ffmpeg_path = crate::ffmpeg::resolve_ffmpeg_path_info().path;
+
+    let mut cache = CAPABILITIES_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(caps) = cache.as_ref() {
+        if caps.ffmpeg_path == ffmpeg_path {
+            return Ok(caps.clone());
+        }
+    }
+
+    let caps = detect_capabilities(ffmpeg_path)?;
+    *cache = Some(caps.clone());
+    Ok(caps)
+}
+
+// Fails fast with a readable message when the resolved FFmpeg definitely
+// lacks something `purpose` needs. If detection itself fails the check
+// passes, so FFmpeg gets to report the real problem instead.
+pub(crate) async fn ensure_supported(
+    requirements: &[(Component, &str)],
+    purpose: &str,
+) -> Result<(), FfmpegError> {
+    let Ok(Ok(caps)) = tokio::task::spawn_blocking(get_capabilities).await else {
+        return Ok(());
+    };
+
+    let missing: Vec<String> = requirements
+        .iter()
+        .filter(|(component, name)| !caps.has(*component, name))
+        .map(|(component, name)| format!("{} {}", name, component.label()))
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(FfmpegError::new(
+            FfmpegErrorKind::MissingCodec,
+            format!(
+                "Your FFmpeg build cannot {}: missing {}. Install a full FFmpeg build or choose a different FFmpeg in Settings.",
+                purpose,
+                missing.join(", ")
+            ),
+        ))
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn get_ffmpeg_capabilities() -> Result<FfmpegCapabilities, String> {
+    tokio::task::spawn_blocking(get_capabilities)
+        .await
+        .map_err(|e| format!("Capability detection failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codec_listing_skips_legend_and_splits_aliases() {
+        let output = "\
+Encoders:
+ V..... = Video
+ A..... = Audio
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+ A....D aac                  AAC (Advanced Audio Coding)
+ S..... srt,subrip           SubRip subtitle
+";
+        let names = parse_codec_listing(output);
+        assert_eq!(
+            names.into_iter().collect::<Vec<_>>(),
+            ["aac", "libx264", "srt", "subrip"]
+        );
+    }
+
+    #[test]
+    fn device_marker_is_a_flag_not_a_name() {
+        let output = " --\n  E  mp4             MP4 (MPEG-4 Part 14)\n  Ed alsa            ALSA audio output\n";
+        let names = parse_codec_listing(output);
+        assert!(names.contains("mp4"));
+        assert!(names.contains("alsa"));
+        assert!(!names.contains("Ed"));
+    }
+
+    #[test]
+    fn codec_listing_without_separator_is_empty() {
+        assert!(parse_codec_listing("Encoders:\n V..... libx264 H.264\n").is_empty());
+    }
+
+    #[test]
+    fn filter_listing_keeps_rows_with_io_column() {
+        let output = "\
+Filters:
+  T.. = Timeline support
+  | = Source or sink filter
+ TSC amix              N->A       Audio mixing.
+ ... scale             V->V       Scale the input video size.
+ ... nullsrc           |->V       Null video source.
+";
+        let names = parse_filter_listing(output);
+        assert_eq!(
+            names.into_iter().collect::<Vec<_>>(),
+            ["amix", "nullsrc", "scale"]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use crate::ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use crate::media_info::{number, text};
+use crate::media_toolkit::{CliToolkit, MediaToolkit};
+use crate::probe_cache::CachedToolkit;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Chapter {
+    // Seconds from the start of the file.
+    pub(crate) start: f64,
+    pub(crate) end: f64,
+    pub(crate) title: Option<String>,
+}
+
+// Parses `ffprobe -show_chapters -of json` output. Chapters without a usable
+// start time are dropped; a missing end is filled in from the next chapter.
+pub(crate) fn parse_chapters(stdout: &str) -> Result<Vec<Chapter>, FfmpegError> {
+    let parsed: Value = serde_json::from_str(stdout).map_err(|e| {
+        FfmpegError::new(
+            FfmpegErrorKind::Other,
+            format!("Failed to parse ffprobe JSON: {} (stdout: {})", e, stdout),
+        )
+    })?;
+
+    let mut chapters: Vec<Chapter> = parsed["chapters"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|chapter| {
+            let start: f64 = number(&chapter["start_time"])?;
+            Some(Chapter {
+                start,
+                end: number(&chapter["end_time"]).unwrap_or(start),
+                title: text(&chapter["tags"]["title"]),
+            })
+        })
+        .collect();
+
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for i in 1..chapters.len() {
+        if chapters[i - 1].end <= chapters[i - 1].start {
+            chapters[i - 1].end = chapters[i].start;
+        }
+    }
+
+    Ok(chapters)
+}
+
+// Chapter markers for the player, in playback order. Empty if the file has
+// none.
+#[tauri::command]
+pub(crate) async fn get_chapters(video_path: String) -> Result<Vec<Chapter>, FfmpegError> {
+    CachedToolkit::new(CliToolkit)
+        .probe_chapters(&video_path)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapters_are_sorted_and_gaps_filled() {
+        let stdout = r#"{"chapters": [
+            {"start_time": "600.000000", "end_time": "1200.000000", "tags": {"title": "Two"}},
+            {"start_time": "0.000000", "tags": {"title": "One"}},
+            {"start_time": "1200.000000", "end_time": "1800.500000"}
+        ]}"#;
+        let chapters = parse_chapters(stdout).unwrap();
+
+        assert_eq!(
+            chapters,
+            [
+                Chapter {
+                    start: 0.0,
+                    end: 600.0,
+                    title: Some("One".to_string()),
+                },
+                Chapter {
+                    start: 600.0,
+                    end: 1200.0,
+                    title: Some("Two".to_string()),
+                },
+                Chapter {
+                    start: 1200.0,
+                    end: 1800.5,
+                    title: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn chapters_without_start_are_dropped() {
+        let stdout = r#"{"chapters": [{"end_time": "10"}, {"start_time": "N/A"}]}"#;
+        assert!(parse_chapters(stdout).unwrap().is_empty());
+        assert!(parse_chapters("{}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn last_chapter_keeps_zero_length_when_end_is_missing() {
+        let chapters = parse_chapters(r#"{"chapters": [{"start_time": 30}]}"#).unwrap();
+        assert_eq!((chapters[0].start, chapters[0].end), (30.0, 30.0));
+    }
+
+    #[test]
+    fn invalid_json_is_an_error() {
+        assert!(parse_chapters("").is_err());
+    }
+}
@@ -0,0 +1,311 @@
+// Scriptable stand-in for `CliToolkit` so command logic can be exercised in
+// tests without FFmpeg installed. Responses are keyed by media path; anything
+// not scripted behaves like a file FFmpeg cannot read.
+
+use crate::chapters::Chapter;
+use crate::ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use crate::integrity::DecodeCheck;
+use crate::loudness::LoudnessMeasurement;
+use crate::media_info::MediaInfo;
+use crate::media_toolkit::{MediaToolkit, ProbedStream, StreamKind};
+use crate::seek_preview::SpriteLayout;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+#[derive(Default)]
+pub(crate) struct FakeToolkit {
+    streams: HashMap<(String, StreamKind), Vec<ProbedStream>>,
+    media: HashMap<String, MediaInfo>,
+    chapters: HashMap<String, Vec<Chapter>>,
+    durations: HashMap<String, f64>,
+    decode_checks: HashMap<String, DecodeCheck>,
+    loudness: HashMap<String, LoudnessMeasurement>,
+    subtitles: HashMap<(String, i64), String>,
+    // Operation name (e.g. "remux_audio_track") to the error it returns.
+    failures: HashMap<&'static str, FfmpegError>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl FakeToolkit {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn with_streams(
+        mut self,
+        path: &str,
+        kind: StreamKind,
+        streams: Vec<ProbedStream>,
+    ) -> Self {
+        self.streams.insert((path.to_string(), kind), streams);
+        self
+    }
+
+    pub(crate) fn with_media_info(mut self, path: &str, info: MediaInfo) -> Self {
+        self.media.insert(path.to_string(), info);
+        self
+    }
+
+    pub(crate) fn with_chapters(mut self, path: &str, chapters: Vec<Chapter>) -> Self {
+        self.chapters.insert(path.to_string(), chapters);
+        self
+    }
+
+    pub(crate) fn with_duration(mut self, path: &str, seconds: f64) -> Self {
+        self.durations.insert(path.to_string(), seconds);
+        self
+    }
+
+    pub(crate) fn with_decode_check(mut self, path: &str, check: DecodeCheck) -> Self {
+        self.decode_checks.insert(path.to_string(), check);
+        self
+    }
+
+    pub(crate) fn with_loudness(mut self, path: &str, loudness: LoudnessMeasurement) -> Self {
+        self.loudness.insert(path.to_string(), loudness);
+        self
+    }
+
+    pub(crate) fn with_subtitle(mut self, path: &str, stream_index: i64, srt: &str) -> Self {
+        self.subtitles
+            .insert((path.to_string(), stream_index), srt.to_string());
+        self
+    }
+
+    // Makes every call to `operation` fail with `error`, regardless of path.
+    pub(crate) fn failing(
+        mut self,
+        operation: &'static str,
+        kind: FfmpegErrorKind,
+        error: &str,
+    ) -> Self {
+        self.failures
+            .insert(operation, FfmpegError::new(kind, error));
+        self
+    }
+
+    // Every call made so far, formatted as "<operation> <args...>".
+    pub(crate) fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn record(&self, operation: &'static str, args: &[&str]) -> Result<(), FfmpegError> {
+        let mut call = operation.to_string();
+        for arg in args {
+            call.push(' ');
+            call.push_str(arg);
+        }
+        self.calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(call);
+
+        match self.failures.get(operation) {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    // Successful writes leave an empty output file behind, like FFmpeg would.
+    fn touch(output: &str) -> Result<(), FfmpegError> {
+        fs::write(output, b"").map_err(|e| {
+            FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("Failed to write {}: {}", output, e),
+            )
+        })
+    }
+
+    fn not_found(context: &str, path: &str) -> FfmpegError {
+        FfmpegError::new(
+            FfmpegErrorKind::FileNotFound,
+            format!("{}: {}: No such file or directory", context, path),
+        )
+    }
+}
+
+impl MediaToolkit for FakeToolkit {
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn probe_streams(
+        &self,
+        path: &str,
+        kind: StreamKind,
+    ) -> Result<Vec<ProbedStream>, FfmpegError> {
+        self.record("probe_streams", &[path, &format!("{:?}", kind)])?;
+        self.streams
+            .get(&(path.to_string(), kind))
+            .cloned()
+            .ok_or_else(|| Self::not_found("ffprobe failed", path))
+    }
+
+    async fn probe_media(&self, path: &str) -> Result<MediaInfo, FfmpegError> {
+        self.record("probe_media", &[path])?;
+        self.media
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Self::not_found("ffprobe failed", path))
+    }
+
+    async fn probe_chapters(&self, path: &str) -> Result<Vec<Chapter>, FfmpegError> {
+        self.record("probe_chapters", &[path])?;
+        self.chapters
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Self::not_found("ffprobe failed", path))
+    }
+
+    async fn probe_duration(&self, path: &str) -> Result<f64, FfmpegError> {
+        self.record("probe_duration", &[path])?;
+        self.durations
+            .get(path)
+            .copied()
+            .ok_or_else(|| Self::not_found("ffprobe failed", path))
+    }
+
+    async fn extract_subtitle(&self, path: &str, stream_index: i64) -> Result<String, FfmpegError> {
+        self.record("extract_subtitle", &[path, &stream_index.to_string()])?;
+        self.subtitles
+            .get(&(path.to_string(), stream_index))
+            .cloned()
+            .ok_or_else(|| {
+                FfmpegError::new(
+                    FfmpegErrorKind::UnsupportedMapping,
+                    format!(
+                        "FFmpeg failed to extract subtitle: stream {} not found",
+                        stream_index
+                    ),
+                )
+            })
+    }
+
+    async fn remux_audio_track(
+        &self,
+        path: &str,
+        audio_stream_index: i64,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.record(
+            "remux_audio_track",
+            &[path, &audio_stream_index.to_string(), output],
+        )?;
+        Self::touch(output)
+    }
+
+    async fn extract_audio(&self, path: &str, output: &str) -> Result<(), FfmpegError> {
+        self.record("extract_audio", &[path, output])?;
+        Self::touch(output)
+    }
+
+    async fn transcode(
+        &self,
+        input: &str,
+        output: &str,
+        target_format: &str,
+    ) -> Result<(), FfmpegError> {
+        self.record("transcode", &[input, output, target_format])?;
+        Self::touch(output)
+    }
+
+    async fn remux(&self, input: &str, output: &str, container: &str) -> Result<(), FfmpegError> {
+        self.record("remux", &[input, output, container])?;
+        Self::touch(output)
+    }
+
+    async fn transcode_audio(
+        &self,
+        input: &str,
+        output: &str,
+        container: &str,
+    ) -> Result<(), FfmpegError> {
+        self.record("transcode_audio", &[input, output, container])?;
+        Self::touch(output)
+    }
+
+    async fn decode_check(
+        &self,
+        path: &str,
+        on_progress: impl Fn(f64) + Send + Sync,
+    ) -> Result<DecodeCheck, FfmpegError> {
+        self.record("decode_check", &[path])?;
+        let check = self
+            .decode_checks
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Self::not_found("FFmpeg failed to verify file", path))?;
+        on_progress(check.decoded_seconds);
+        Ok(check)
+    }
+
+    async fn measure_loudness(&self, path: &str) -> Result<LoudnessMeasurement, FfmpegError> {
+        self.record("measure_loudness", &[path])?;
+        self.loudness
+            .get(path)
+            .copied()
+            .ok_or_else(|| Self::not_found("FFmpeg failed to measure loudness", path))
+    }
+
+    async fn extract_cover_art(
+        &self,
+        path: &str,
+        stream_index: i64,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.record(
+            "extract_cover_art",
+            &[path, &stream_index.to_string(), output],
+        )?;
+        Self::touch(output)
+    }
+
+    async fn extract_frame(
+        &self,
+        path: &str,
+        time: f64,
+        width: u32,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.record(
+            "extract_frame",
+            &[path, &time.to_string(), &width.to_string(), output],
+        )?;
+        Self::touch(output)
+    }
+
+    async fn render_sprite_sheet(
+        &self,
+        path: &str,
+        layout: SpriteLayout,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.record(
+            "render_sprite_sheet",
+            &[path, &format!("{:?}", layout), output],
+        )?;
+        Self::touch(output)
+    }
+
+    async fn render_hover_preview(
+        &self,
+        path: &str,
+        starts: &[f64],
+        sample_secs: f64,
+        width: u32,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.record(
+            "render_hover_preview",
+            &[
+                path,
+                &format!("{:?}", starts),
+                &sample_secs.to_string(),
+                &width.to_string(),
+                output,
+            ],
+        )?;
+        Self::touch(output)
+    }
+}
@@ -1,39 +1,205 @@
+use crate::process_scheduler::{self, Priority};
 use serde::Serialize;
-use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Resolved binaries are cached for the process lifetime. Probing runs
+// `ffmpeg -version` for the custom path and potentially every PATH entry,
+// which is far too slow to repeat for every ffprobe call in a library scan.
+static FFMPEG_CACHE: Mutex<Option<FfmpegPathInfo>> = Mutex::new(None);
+static FFPROBE_CACHE: Mutex<Option<FfprobePathInfo>> = Mutex::new(None);
+
+// Oldest release every feature works with. Keep in step with the newest entry
+// in VERSIONED_FEATURES.
+const MIN_FFMPEG_VERSION: (u32, u32) = (4, 0);
+
+// Features that depend on the FFmpeg release rather than on which encoders or
+// muxers happen to be compiled in (capabilities.rs covers those).
+const VERSIONED_FEATURES: &[((u32, u32), &str)] = &[
+    // The native AAC encoder was experimental and refused to run without
+    // `-strict experimental` before 3.0.
+    ((3, 0), "MP4 conversion with the built-in AAC encoder"),
+    // libaom-av1 decoding arrived in 4.0.
+    ((4, 0), "Processing AV1 video"),
+];
 
 #[derive(Clone, Serialize)]
 pub struct FfmpegPathInfo {
     pub path: Option<String>,
     pub is_custom: bool,
+    // Installed by `install_managed_ffmpeg` into the app data directory.
+    pub is_managed: bool,
+    // Version string as printed by `ffmpeg -version`, e.g. "6.1.1-3ubuntu5".
+    pub version: Option<String>,
+    // Numeric (major, minor, patch); None for git snapshots like "N-113000-g…".
+    #[serde(skip)]
+    pub version_number: Option<(u32, u32, u32)>,
+    // `--enable-*`/`--disable-*` flags from the "configuration:" line.
+    pub configuration: Vec<String>,
+    // Derived from the configuration, e.g. "GPL v3" or "LGPL v2.1".
+    pub license: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FfprobePathInfo {
+    pub path: Option<String>,
+    pub is_custom: bool,
+}
+
+impl FfmpegPathInfo {
+    fn not_found() -> Self {
+        FfmpegPathInfo {
+            path: None,
+            is_custom: false,
+            is_managed: false,
+            version: None,
+            version_number: None,
+            configuration: Vec::new(),
+            license: None,
+        }
+    }
+
+    fn from_version_output(path: String, is_custom: bool, output: &str) -> Self {
+        let version = output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("ffmpeg version "))
+            .and_then(|rest| rest.split_whitespace().next())
+            .map(str::to_string);
+        let configuration = output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("configuration:"))
+            .map(parse_configuration)
+            .unwrap_or_default();
+        let license = license_from_configuration(&configuration);
+
+        FfmpegPathInfo {
+            path: Some(path),
+            is_custom,
+            is_managed: false,
+            version_number: version.as_deref().and_then(parse_version_number),
+            version,
+            license: (!configuration.is_empty()).then_some(license),
+            configuration,
+        }
+    }
+
+    // Features that will not work with this build because it is older than
+    // they need. Empty when the version is unknown, since git snapshots are
+    // almost always recent.
+    pub(crate) fn unsupported_features(&self) -> Vec<String> {
+        let Some((major, minor, _)) = self.version_number else {
+            return Vec::new();
+        };
+        VERSIONED_FEATURES
+            .iter()
+            .filter(|(required, _)| (major, minor) < *required)
+            .map(|(_, feature)| feature.to_string())
+            .collect()
+    }
+
+    pub(crate) fn is_degraded(&self) -> bool {
+        self.version_number
+            .is_some_and(|(major, minor, _)| (major, minor) < MIN_FFMPEG_VERSION)
+    }
+}
+
+pub(crate) fn min_ffmpeg_version() -> String {
+    format!("{}.{}", MIN_FFMPEG_VERSION.0, MIN_FFMPEG_VERSION.1)
+}
+
+// Release builds print "6.1.1-3ubuntu5", "n7.0" or "7.0.2-full_build-…";
+// git snapshots ("N-113000-g…", "2023-06-04-git-…") have no release number.
+fn parse_version_number(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.strip_prefix('n').unwrap_or(version);
+    let numeric: &str = version
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()?;
+    let mut parts = numeric.split('.').map(|part| part.parse::<u32>().ok());
+    let major = parts.next()??;
+    let minor = parts.next()??;
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+// Splits the configure line into flags. Values containing spaces (e.g.
+// `--extra-cflags='-O2 -g'`) are re-joined onto the flag they belong to.
+fn parse_configuration(line: &str) -> Vec<String> {
+    let mut flags: Vec<String> = Vec::new();
+    for token in line.split_whitespace() {
+        match flags.last_mut() {
+            Some(last) if !token.starts_with("--") => {
+                last.push(' ');
+                last.push_str(token);
+            }
+            _ => flags.push(token.to_string()),
+        }
+    }
+    flags
+}
+
+fn license_from_configuration(configuration: &[String]) -> String {
+    let enabled = |flag: &str| configuration.iter().any(|f| f == flag);
+    if enabled("--enable-nonfree") {
+        "Non-free (not redistributable)".to_string()
+    } else {
+        match (enabled("--enable-gpl"), enabled("--enable-version3")) {
+            (true, true) => "GPL v3",
+            (true, false) => "GPL v2",
+            (false, true) => "LGPL v3",
+            (false, false) => "LGPL v2.1",
+        }
+        .to_string()
+    }
 }
 
 pub(crate) fn get_ffmpeg_custom_path_from_config() -> Option<String> {
-    let home = dirs::home_dir()?;
-    let config_file = home.join(".glucose").join("config.json");
-    let content = fs::read_to_string(&config_file).ok()?;
-    let config: serde_json::Value = serde_json::from_str(&content).ok()?;
-    config
-        .get("ffmpeg_custom_path")
-        .and_then(|v| v.as_str())
+    crate::settings::load_settings()
+        .ok()?
+        .ffmpeg_custom_path
         .filter(|s| !s.is_empty())
-        .map(String::from)
+}
+
+pub(crate) fn get_ffprobe_custom_path_from_config() -> Option<String> {
+    crate::settings::load_settings()
+        .ok()?
+        .ffprobe_custom_path
+        .filter(|s| !s.is_empty())
+}
+
+// Runs `<path> -version` and returns its output if it carries the tool's
+// banner, e.g. "ffprobe version". Guards against picking the wrong binary as
+// well as broken ones.
+fn version_output(path: &Path, tool: &str) -> Option<String> {
+    if !path.is_file() {
+        return None;
+    }
+    let _permit = process_scheduler::acquire_blocking(Priority::Interactive).ok()?;
+    let output = crate::create_hidden_command(path.to_string_lossy().as_ref())
+        .arg("-version")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let banner = format!("{} version", tool);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stdout.contains(&banner) {
+        Some(stdout.to_string())
+    } else if stderr.contains(&banner) {
+        Some(stderr.to_string())
+    } else {
+        None
+    }
 }
 
 fn validates_as_ffmpeg(path: &Path) -> bool {
-    path.is_file()
-        && crate::create_hidden_command(path.to_string_lossy().as_ref())
-            .arg("-version")
-            .output()
-            .map(|o| {
-                if !o.status.success() {
-                    return false;
-                }
-                let stdout = String::from_utf8_lossy(&o.stdout);
-                let stderr = String::from_utf8_lossy(&o.stderr);
-                stdout.contains("ffmpeg version") || stderr.contains("ffmpeg version")
-            })
-            .unwrap_or(false)
+    version_output(path, "ffmpeg").is_some()
+}
+
+fn validates_as_ffprobe(path: &Path) -> bool {
+    version_output(path, "ffprobe").is_some()
 }
 
 pub(crate) fn validate_ffmpeg_path(path: &str) -> Result<(), String> {
@@ -60,6 +226,30 @@ pub(crate) fn validate_ffmpeg_path(path: &str) -> Result<(), String> {
     Ok(())
 }
 
+pub(crate) fn validate_ffprobe_path(path: &str) -> Result<(), String> {
+    let candidate = Path::new(path);
+
+    if !candidate.exists() {
+        return Err(format!("FFprobe path does not exist: {}", path));
+    }
+
+    if !candidate.is_file() {
+        return Err(format!(
+            "FFprobe path must point to an executable file: {}",
+            path
+        ));
+    }
+
+    if !validates_as_ffprobe(candidate) {
+        return Err(format!(
+            "Selected file is not a working FFprobe executable: {}",
+            path
+        ));
+    }
+
+    Ok(())
+}
+
 fn executable_candidates(dir: &Path, name: &str) -> Vec<PathBuf> {
     let base = dir.join(name);
 
@@ -84,13 +274,15 @@ fn executable_candidates(dir: &Path, name: &str) -> Vec<PathBuf> {
     }
 }
 
-fn resolve_from_path(name: &str) -> Option<String> {
+// Returns the first working `name` on PATH together with its `-version`
+// output.
+fn resolve_from_path(name: &str) -> Option<(String, String)> {
     let path_var = std::env::var_os("PATH")?;
 
     for dir in std::env::split_paths(&path_var) {
         for candidate in executable_candidates(&dir, name) {
-            if validates_as_ffmpeg(&candidate) {
-                return Some(candidate.to_string_lossy().to_string());
+            if let Some(output) = version_output(&candidate, name) {
+                return Some((candidate.to_string_lossy().to_string(), output));
             }
         }
     }
@@ -98,45 +290,142 @@ fn resolve_from_path(name: &str) -> Option<String> {
     None
 }
 
+// Drops the cached ffmpeg/ffprobe resolution so the next command re-probes.
+// Called whenever the custom path changes.
+pub(crate) fn invalidate_ffmpeg_cache() {
+    *FFMPEG_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    *FFPROBE_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    crate::capabilities::invalidate_capabilities_cache();
+}
+
+// Resolves the ffmpeg binary: custom config path first, then the managed copy
+// from `install_managed_ffmpeg`, then bundled AppData, then system PATH. The
+// lock is held while probing so concurrent callers wait for one result
+// instead of all spawning their own probes.
 pub(crate) fn resolve_ffmpeg_path_info() -> FfmpegPathInfo {
-    if let Some(custom) = get_ffmpeg_custom_path_from_config() {
-        if validate_ffmpeg_path(&custom).is_ok() {
-            return FfmpegPathInfo {
+    FFMPEG_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(probe_ffmpeg_path_info)
+        .clone()
+}
+
+// Resolves ffprobe independently of ffmpeg: custom config path first, then
+// next to the resolved ffmpeg, then the managed copy, then the bundled AppData
+// copy on Windows, then system PATH. Takes FFMPEG_CACHE while holding
+// FFPROBE_CACHE, never the other way round.
+pub(crate) fn resolve_ffprobe_path_info() -> FfprobePathInfo {
+    FFPROBE_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(probe_ffprobe_path_info)
+        .clone()
+}
+
+// Path to run ffprobe with. Falls back to a bare `ffprobe` when nothing
+// validated so the spawn error names the missing tool.
+pub(crate) fn resolve_ffprobe_path() -> String {
+    resolve_ffprobe_path_info()
+        .path
+        .unwrap_or_else(|| "ffprobe".to_string())
+}
+
+fn probe_ffprobe_path_info() -> FfprobePathInfo {
+    let found = |path: String| FfprobePathInfo {
+        path: Some(path),
+        is_custom: false,
+    };
+
+    if let Some(custom) = get_ffprobe_custom_path_from_config() {
+        if validate_ffprobe_path(&custom).is_ok() {
+            return FfprobePathInfo {
                 path: Some(custom),
                 is_custom: true,
             };
         }
     }
 
+    if let Some(ffmpeg_path) = resolve_ffmpeg_path_info().path {
+        if let Some(bin_dir) = Path::new(&ffmpeg_path).parent() {
+            for candidate in executable_candidates(bin_dir, "ffprobe") {
+                if validates_as_ffprobe(&candidate) {
+                    return found(candidate.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+
+    if let Some(managed) = crate::managed_ffmpeg::managed_binary("ffprobe") {
+        if validates_as_ffprobe(&managed) {
+            return found(managed.to_string_lossy().to_string());
+        }
+    }
+
     #[cfg(target_os = "windows")]
     {
         if let Ok(app_data) = std::env::var("LOCALAPPDATA") {
-            let ffmpeg_exe = std::path::Path::new(&app_data)
+            let ffprobe_exe = Path::new(&app_data)
                 .join("glucose")
                 .join("resources")
                 .join("ffmpeg")
                 .join("bin")
-                .join("ffmpeg.exe");
-            if validates_as_ffmpeg(&ffmpeg_exe) {
-                return FfmpegPathInfo {
-                    path: Some(ffmpeg_exe.to_string_lossy().to_string()),
-                    is_custom: false,
-                };
+                .join("ffprobe.exe");
+            if validates_as_ffprobe(&ffprobe_exe) {
+                return found(ffprobe_exe.to_string_lossy().to_string());
             }
         }
     }
 
-    if let Some(path) = resolve_from_path("ffmpeg") {
-        FfmpegPathInfo {
-            path: Some(path),
-            is_custom: false,
+    if let Some((path, _)) = resolve_from_path("ffprobe") {
+        return found(path);
+    }
+
+    FfprobePathInfo {
+        path: None,
+        is_custom: false,
+    }
+}
+
+fn probe_ffmpeg_path_info() -> FfmpegPathInfo {
+    if let Some(custom) = get_ffmpeg_custom_path_from_config() {
+        if let Some(output) = version_output(Path::new(&custom), "ffmpeg") {
+            return FfmpegPathInfo::from_version_output(custom, true, &output);
         }
-    } else {
-        FfmpegPathInfo {
-            path: None,
-            is_custom: false,
+    }
+
+    if let Some(managed) = crate::managed_ffmpeg::managed_binary("ffmpeg") {
+        if let Some(output) = version_output(&managed, "ffmpeg") {
+            let path = managed.to_string_lossy().to_string();
+            return FfmpegPathInfo {
+                is_managed: true,
+                ..FfmpegPathInfo::from_version_output(path, false, &output)
+            };
         }
     }
+
+    #[cfg(target_os = "windows")]
+    {
+        if let Ok(app_data) = std::env::var("LOCALAPPDATA") {
+            let ffmpeg_exe = std::path::Path::new(&app_data)
+                .join("glucose")
+                .join("resources")
+                .join("ffmpeg")
+                .join("bin")
+                .join("ffmpeg.exe");
+            if let Some(output) = version_output(&ffmpeg_exe, "ffmpeg") {
+                return FfmpegPathInfo::from_version_output(
+                    ffmpeg_exe.to_string_lossy().to_string(),
+                    false,
+                    &output,
+                );
+            }
+        }
+    }
+
+    match resolve_from_path("ffmpeg") {
+        Some((path, output)) => FfmpegPathInfo::from_version_output(path, false, &output),
+        None => FfmpegPathInfo::not_found(),
+    }
 }
 
 #[tauri::command]
@@ -144,6 +433,19 @@ pub fn get_ffmpeg_path() -> Result<FfmpegPathInfo, String> {
     Ok(resolve_ffmpeg_path_info())
 }
 
+// Re-probes ffmpeg/ffprobe, e.g. after the user installs FFmpeg while the app
+// is running.
+#[tauri::command]
+pub fn refresh_ffmpeg_path() -> Result<FfmpegPathInfo, String> {
+    invalidate_ffmpeg_cache();
+    Ok(resolve_ffmpeg_path_info())
+}
+
+#[tauri::command]
+pub fn get_ffprobe_path() -> Result<FfprobePathInfo, String> {
+    Ok(resolve_ffprobe_path_info())
+}
+
 #[tauri::command]
 pub async fn pick_ffmpeg_executable(app: tauri::AppHandle) -> Result<Option<String>, String> {
     use tauri_plugin_dialog::DialogExt;
@@ -172,44 +474,82 @@ pub async fn pick_ffmpeg_executable(app: tauri::AppHandle) -> Result<Option<Stri
 
 #[tauri::command]
 pub fn save_ffmpeg_custom_path(path: Option<String>) -> Result<(), String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".glucose");
-    let config_file = config_dir.join("config.json");
+    if let Some(p) = &path {
+        validate_ffmpeg_path(p)?;
+    }
 
-    fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    crate::settings::modify_settings(|settings| {
+        settings.ffmpeg_custom_path = path;
+        Ok(())
+    })?;
 
-    let _guard = crate::CONFIG_MUTEX
-        .lock()
-        .unwrap_or_else(|e| e.into_inner());
+    Ok(())
+}
 
-    let mut config: serde_json::Value = if config_file.exists() {
-        let content = fs::read_to_string(&config_file)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?
-    } else {
-        serde_json::json!({})
-    };
+#[tauri::command]
+pub fn save_ffprobe_custom_path(path: Option<String>) -> Result<(), String> {
+    if let Some(p) = &path {
+        validate_ffprobe_path(p)?;
+    }
 
-    let config_object = config
-        .as_object_mut()
-        .ok_or_else(|| "Config root must be a JSON object".to_string())?;
+    crate::settings::modify_settings(|settings| {
+        settings.ffprobe_custom_path = path;
+        Ok(())
+    })?;
 
-    match path {
-        Some(p) => {
-            validate_ffmpeg_path(&p)?;
-            config_object.insert("ffmpeg_custom_path".to_string(), serde_json::json!(p));
-        }
-        None => {
-            config_object.remove("ffmpeg_custom_path");
-        }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_versions_parse() {
+        assert_eq!(parse_version_number("6.1.1-3ubuntu5"), Some((6, 1, 1)));
+        assert_eq!(parse_version_number("n7.0"), Some((7, 0, 0)));
+        assert_eq!(
+            parse_version_number("7.0.2-full_build-www.gyan.dev"),
+            Some((7, 0, 2))
+        );
     }
 
-    let content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-    let temp_file = config_file.with_extension("json.tmp");
-    fs::write(&temp_file, &content).map_err(|e| format!("Failed to write temp config: {}", e))?;
-    fs::rename(&temp_file, &config_file).map_err(|e| format!("Failed to replace config: {}", e))?;
+    #[test]
+    fn git_snapshots_have_no_version_number() {
+        assert_eq!(parse_version_number("N-113000-g1234abcd"), None);
+        assert_eq!(parse_version_number("2023-06-04-git-abc"), None);
+        assert_eq!(parse_version_number("7"), None);
+    }
 
-    Ok(())
+    #[test]
+    fn configuration_rejoins_values_with_spaces() {
+        let flags = parse_configuration(
+            " --prefix=/usr --extra-cflags='-O2 -g' --enable-gpl --enable-version3",
+        );
+        assert_eq!(
+            flags,
+            [
+                "--prefix=/usr",
+                "--extra-cflags='-O2 -g'",
+                "--enable-gpl",
+                "--enable-version3"
+            ]
+        );
+        assert_eq!(license_from_configuration(&flags), "GPL v3");
+    }
+
+    #[test]
+    fn version_output_is_parsed_in_full() {
+        let output = "ffmpeg version 3.4.8 Copyright (c) 2000-2020\n  built with gcc 7\n  configuration: --enable-nonfree\n";
+        let info =
+            FfmpegPathInfo::from_version_output("/usr/bin/ffmpeg".to_string(), false, output);
+
+        assert_eq!(info.version.as_deref(), Some("3.4.8"));
+        assert!(info.is_degraded());
+        assert_eq!(info.unsupported_features(), ["Processing AV1 video"]);
+        assert_eq!(
+            info.license.as_deref(),
+            Some("Non-free (not redistributable)")
+        );
+    }
 }
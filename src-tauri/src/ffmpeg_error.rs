@@ -0,0 +1,307 @@
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+// Longest stderr excerpt attached to an error. FFmpeg can print thousands of
+// lines for a corrupt file; the UI and logs only need the part that matters.
+const MAX_EXCERPT_CHARS: usize = 600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FfmpegErrorKind {
+    // The ffmpeg/ffprobe binary could not be started at all.
+    NotInstalled,
+    // Encoder, decoder or container format not compiled into this build.
+    MissingCodec,
+    InvalidData,
+    PermissionDenied,
+    NoSpace,
+    FileNotFound,
+    // `-map` selected a stream that does not exist or the output container
+    // cannot hold.
+    UnsupportedMapping,
+    Timeout,
+    Other,
+}
+
+impl FfmpegErrorKind {
+    // What the user can do about it. None for Other, where the stderr excerpt
+    // is the only useful information.
+    fn advice(self) -> Option<&'static str> {
+        match self {
+            FfmpegErrorKind::NotInstalled => Some(
+                "FFmpeg could not be started. Make sure FFmpeg is installed or choose it in Settings.",
+            ),
+            FfmpegErrorKind::MissingCodec => Some(
+                "Your FFmpeg build is missing a required codec or format. Install a full FFmpeg build or choose a different FFmpeg in Settings.",
+            ),
+            FfmpegErrorKind::InvalidData => {
+                Some("The file appears to be damaged or is not a supported media file.")
+            }
+            FfmpegErrorKind::PermissionDenied => Some(
+                "Permission denied. Check that Glucose can read the file and write to its folder.",
+            ),
+            FfmpegErrorKind::NoSpace => {
+                Some("Not enough disk space. Free up some space and try again.")
+            }
+            FfmpegErrorKind::FileNotFound => {
+                Some("The file could not be found. It may have been moved or deleted.")
+            }
+            FfmpegErrorKind::UnsupportedMapping => Some(
+                "The selected stream is missing or cannot be stored in the output format.",
+            ),
+            FfmpegErrorKind::Timeout | FfmpegErrorKind::Other => None,
+        }
+    }
+}
+
+// Lower-case stderr fragments for each kind, checked in order. Mapping comes
+// before MissingCodec because "codec not currently supported in container"
+// is about the output format, not the build.
+const STDERR_PATTERNS: &[(FfmpegErrorKind, &[&str])] = &[
+    (
+        FfmpegErrorKind::NoSpace,
+        &["no space left on device", "disk quota exceeded"],
+    ),
+    (
+        FfmpegErrorKind::PermissionDenied,
+        &[
+            "permission denied",
+            "operation not permitted",
+            "access is denied",
+        ],
+    ),
+    (
+        FfmpegErrorKind::FileNotFound,
+        &["no such file or directory", "the system cannot find the"],
+    ),
+    (
+        FfmpegErrorKind::UnsupportedMapping,
+        &[
+            "matches no streams",
+            "invalid stream specifier",
+            "does not contain any stream",
+            "could not find tag for codec",
+            "not currently supported in container",
+        ],
+    ),
+    (
+        FfmpegErrorKind::MissingCodec,
+        &[
+            "unknown encoder",
+            "unknown decoder",
+            "not found for output stream",
+            "decoder (codec",
+            "unable to find a suitable output format",
+            "requested output format",
+        ],
+    ),
+    (
+        FfmpegErrorKind::InvalidData,
+        &[
+            "invalid data found when processing input",
+            "moov atom not found",
+            "ebml header parsing failed",
+            "error while decoding",
+            "invalid nal unit",
+            "packet corrupt",
+            "corrupt input packet",
+            "corrupt decoded frame",
+            "file ended prematurely",
+            "truncating packet",
+        ],
+    ),
+];
+
+// Media commands return this as-is, so the frontend gets the kind to react to
+// as well as the message to show.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct FfmpegError {
+    pub(crate) kind: FfmpegErrorKind,
+    message: String,
+    // The stderr lines that explain the failure.
+    excerpt: Option<String>,
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_EXCERPT_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(MAX_EXCERPT_CHARS).collect();
+    format!("{}…", cut)
+}
+
+// Finds the first stderr line matching a known pattern. Unclassified output
+// keeps its last few lines, which is where FFmpeg puts the fatal error.
+fn classify(stderr: &str) -> (FfmpegErrorKind, Option<String>) {
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    for (kind, patterns) in STDERR_PATTERNS {
+        for line in &lines {
+            let lower = line.to_lowercase();
+            if patterns.iter().any(|p| lower.contains(p)) {
+                return (*kind, Some(truncate(line)));
+            }
+        }
+    }
+
+    let tail = lines[lines.len().saturating_sub(3)..].join("\n");
+    (
+        FfmpegErrorKind::Other,
+        (!tail.is_empty()).then(|| truncate(&tail)),
+    )
+}
+
+fn with_advice(context: &str, kind: FfmpegErrorKind) -> String {
+    match kind.advice() {
+        Some(advice) => format!("{}: {}", context, advice),
+        None => context.to_string(),
+    }
+}
+
+impl FfmpegError {
+    pub(crate) fn new(kind: FfmpegErrorKind, message: impl Into<String>) -> Self {
+        FfmpegError {
+            kind,
+            message: message.into(),
+            excerpt: None,
+        }
+    }
+
+    // Classifies a failed run from its stderr. `context` says what was being
+    // attempted, e.g. "FFmpeg remux failed".
+    pub(crate) fn from_stderr(context: &str, stderr: &[u8]) -> Self {
+        let stderr = String::from_utf8_lossy(stderr);
+        let (kind, excerpt) = classify(&stderr);
+
+        #[cfg(debug_assertions)]
+        eprintln!(
+            "[FFmpeg Error] {} ({:?}): {}",
+            context,
+            kind,
+            excerpt.as_deref().unwrap_or("<no output>")
+        );
+
+        FfmpegError {
+            kind,
+            message: with_advice(context, kind),
+            excerpt,
+        }
+    }
+
+    pub(crate) fn spawn_failed(tool: &str, err: std::io::Error) -> Self {
+        let kind = match err.kind() {
+            std::io::ErrorKind::PermissionDenied => FfmpegErrorKind::PermissionDenied,
+            _ => FfmpegErrorKind::NotInstalled,
+        };
+        FfmpegError {
+            kind,
+            message: with_advice(&format!("Failed to run {}", tool), kind),
+            excerpt: Some(err.to_string()),
+        }
+    }
+
+    pub(crate) fn timed_out(tool: &str, timeout: Duration) -> Self {
+        FfmpegError::new(
+            FfmpegErrorKind::Timeout,
+            format!("{} timed out after {} seconds", tool, timeout.as_secs()),
+        )
+    }
+}
+
+impl fmt::Display for FfmpegError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(excerpt) = &self.excerpt {
+            write!(f, "\n\nDetails: {}", excerpt)?;
+        }
+        Ok(())
+    }
+}
+
+// Commands that return `Result<_, String>` get the message shown to the user
+// from `?`.
+impl From<FfmpegError> for String {
+    fn from(err: FfmpegError) -> String {
+        err.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_matching_line_is_the_excerpt() {
+        let stderr = "\
+ffmpeg version 6.1
+Input #0, matroska,webm, from 'film.mkv':
+[out#0/mp4 @ 0x1] Could not find tag for codec subrip in stream #2, codec not currently supported in container
+Conversion failed!
+";
+        let (kind, excerpt) = classify(stderr);
+        assert_eq!(kind, FfmpegErrorKind::UnsupportedMapping);
+        assert!(excerpt
+            .unwrap()
+            .starts_with("[out#0/mp4 @ 0x1] Could not find tag"));
+    }
+
+    #[test]
+    fn earlier_kinds_win_over_later_ones() {
+        let stderr = "Error while decoding stream #0:0\nav_interleaved_write_frame(): No space left on device\n";
+        assert_eq!(classify(stderr).0, FfmpegErrorKind::NoSpace);
+    }
+
+    #[test]
+    fn damaged_streams_are_invalid_data() {
+        for line in [
+            "[h264 @ 0x1] corrupt decoded frame in stream 0",
+            "[mpegts @ 0x1] Packet corrupt (stream = 0, dts = 900)",
+            "[mov,mp4 @ 0x1] moov atom not found",
+            "film.mp4: File ended prematurely",
+        ] {
+            assert_eq!(classify(line).0, FfmpegErrorKind::InvalidData, "{}", line);
+        }
+    }
+
+    #[test]
+    fn file_names_do_not_look_like_damage() {
+        let stderr =
+            "Input #0, mov,mp4, from '/videos/corrupted-truncated.mp4':\nsomething else went wrong";
+        assert_eq!(classify(stderr).0, FfmpegErrorKind::Other);
+    }
+
+    #[test]
+    fn unclassified_output_keeps_last_lines() {
+        let (kind, excerpt) = classify("one\ntwo\n\nthree\nfour\n");
+        assert_eq!(kind, FfmpegErrorKind::Other);
+        assert_eq!(excerpt.as_deref(), Some("two\nthree\nfour"));
+        assert_eq!(classify("").1, None);
+    }
+
+    #[test]
+    fn long_excerpts_are_truncated() {
+        let line = format!(
+            "Invalid data found when processing input {}",
+            "x".repeat(1000)
+        );
+        let (_, excerpt) = classify(&line);
+        assert_eq!(excerpt.unwrap().chars().count(), MAX_EXCERPT_CHARS + 1);
+    }
+
+    #[test]
+    fn serializes_kind_message_and_excerpt() {
+        let err = FfmpegError::from_stderr("FFmpeg remux failed", b"No space left on device");
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["kind"], "no_space");
+        assert!(json["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("FFmpeg remux failed: Not enough disk space"));
+        assert_eq!(json["excerpt"], "No space left on device");
+    }
+}
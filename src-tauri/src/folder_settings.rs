@@ -0,0 +1,245 @@
+use crate::settings::lenient;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Sidecar file that overrides playback defaults for a folder and everything
+// below it. Dot-prefixed so the library scan skips it.
+const SIDECAR_FILE_NAME: &str = ".glucose.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum EndBehavior {
+    Nothing,
+    Loop,
+    Next,
+}
+
+// Playback defaults that can be set globally in config.json and overridden per
+// folder. Unset fields fall through to the next level up.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct MediaPreferences {
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) audio_language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) subtitle_language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) whisper_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) whisper_language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) end_behavior: Option<EndBehavior>,
+}
+
+impl MediaPreferences {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == MediaPreferences::default()
+    }
+
+    // Applies every field that `other` sets on top of `self`.
+    fn overlay(&mut self, other: &MediaPreferences) {
+        if other.audio_language.is_some() {
+            self.audio_language = other.audio_language.clone();
+        }
+        if other.subtitle_language.is_some() {
+            self.subtitle_language = other.subtitle_language.clone();
+        }
+        if other.whisper_model.is_some() {
+            self.whisper_model = other.whisper_model.clone();
+        }
+        if other.whisper_language.is_some() {
+            self.whisper_language = other.whisper_language.clone();
+        }
+        if other.end_behavior.is_some() {
+            self.end_behavior = other.end_behavior;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FolderSidecar {
+    // Like EditorConfig's `root = true`: stop looking in parent folders.
+    #[serde(default)]
+    root: bool,
+    #[serde(flatten)]
+    preferences: MediaPreferences,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct EffectiveMediaSettings {
+    pub(crate) preferences: MediaPreferences,
+    // Sidecar files that contributed, outermost first.
+    sources: Vec<String>,
+}
+
+fn read_sidecar(path: &Path) -> Option<FolderSidecar> {
+    let content = fs::read_to_string(path).ok()?;
+    // A typo in one folder's sidecar should not stop playback.
+    serde_json::from_str(&content)
+        .inspect_err(|_err| {
+            #[cfg(debug_assertions)]
+            eprintln!("[Folder Settings] Ignoring {}: {}", path.display(), _err);
+        })
+        .ok()
+}
+
+// Merges global preferences with every `.glucose.json` found walking up from
+// the media file's directory. Nearer folders win over farther ones.
+pub(crate) fn resolve_media_preferences(media_path: &str) -> EffectiveMediaSettings {
+    let global = crate::settings::load_settings()
+        .map(|settings| settings.media)
+        .unwrap_or_default();
+    resolve_over(media_path, global)
+}
+
+fn resolve_over(media_path: &str, global: MediaPreferences) -> EffectiveMediaSettings {
+    let mut sidecars = Vec::new();
+    if let Some(dir) = Path::new(media_path).parent() {
+        for ancestor in dir.ancestors() {
+            let candidate = ancestor.join(SIDECAR_FILE_NAME);
+            let Some(sidecar) = read_sidecar(&candidate) else {
+                continue;
+            };
+            let is_root = sidecar.root;
+            sidecars.push((candidate, sidecar));
+            if is_root {
+                break;
+            }
+        }
+    }
+
+    let mut preferences = global;
+    let mut sources = Vec::new();
+    for (path, sidecar) in sidecars.iter().rev() {
+        preferences.overlay(&sidecar.preferences);
+        sources.push(path.to_string_lossy().to_string());
+    }
+
+    EffectiveMediaSettings {
+        preferences,
+        sources,
+    }
+}
+
+#[tauri::command]
+pub(crate) fn get_effective_settings(video_path: String) -> Result<EffectiveMediaSettings, String> {
+    Ok(resolve_media_preferences(&video_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // A library folder with a `show` subfolder, each optionally holding a
+    // sidecar with the given contents.
+    fn library(name: &str, outer: Option<&str>, inner: Option<&str>) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("folder-settings-{}-{}", name, std::process::id()));
+        let show = root.join("show");
+        fs::create_dir_all(&show).unwrap();
+        if let Some(content) = outer {
+            fs::write(root.join(SIDECAR_FILE_NAME), content).unwrap();
+        }
+        if let Some(content) = inner {
+            fs::write(show.join(SIDECAR_FILE_NAME), content).unwrap();
+        }
+        root
+    }
+
+    fn episode(root: &Path) -> String {
+        root.join("show")
+            .join("ep1.mkv")
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn nearest_sidecar_wins() {
+        let root = library(
+            "nearest",
+            Some(r#"{"root": true, "audio_language": "eng", "subtitle_language": "eng"}"#),
+            Some(r#"{"audio_language": "jpn"}"#),
+        );
+
+        let effective = resolve_over(&episode(&root), MediaPreferences::default());
+        assert_eq!(effective.preferences.audio_language.as_deref(), Some("jpn"));
+        assert_eq!(
+            effective.preferences.subtitle_language.as_deref(),
+            Some("eng")
+        );
+        assert_eq!(
+            effective.sources,
+            [
+                root.join(SIDECAR_FILE_NAME).to_string_lossy(),
+                root.join("show").join(SIDECAR_FILE_NAME).to_string_lossy(),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn unset_fields_fall_through() {
+        let mut preferences = MediaPreferences {
+            whisper_model: Some("base".to_string()),
+            end_behavior: Some(EndBehavior::Nothing),
+            ..MediaPreferences::default()
+        };
+        preferences.overlay(&MediaPreferences {
+            end_behavior: Some(EndBehavior::Next),
+            ..MediaPreferences::default()
+        });
+
+        assert_eq!(preferences.whisper_model.as_deref(), Some("base"));
+        assert_eq!(preferences.end_behavior, Some(EndBehavior::Next));
+        assert!(preferences.audio_language.is_none());
+    }
+
+    #[test]
+    fn root_sidecar_stops_the_walk() {
+        let root = library(
+            "root",
+            Some(r#"{"audio_language": "eng"}"#),
+            Some(r#"{"root": true, "subtitle_language": "fre"}"#),
+        );
+
+        let global = MediaPreferences {
+            whisper_language: Some("auto".to_string()),
+            ..MediaPreferences::default()
+        };
+        let effective = resolve_over(&episode(&root), global);
+        assert!(effective.preferences.audio_language.is_none());
+        assert_eq!(
+            effective.preferences.subtitle_language.as_deref(),
+            Some("fre")
+        );
+        assert_eq!(
+            effective.preferences.whisper_language.as_deref(),
+            Some("auto")
+        );
+        assert_eq!(effective.sources.len(), 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn malformed_sidecar_is_ignored() {
+        let root = library(
+            "malformed",
+            Some(r#"{"root": true, "end_behavior": "loop"}"#),
+            Some(r#"{"audio_language": "jpn""#),
+        );
+
+        let effective = resolve_over(&episode(&root), MediaPreferences::default());
+        assert_eq!(effective.preferences.end_behavior, Some(EndBehavior::Loop));
+        assert!(effective.preferences.audio_language.is_none());
+        assert_eq!(
+            effective.sources,
+            [root.join(SIDECAR_FILE_NAME).to_string_lossy()]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::capabilities::{self, Component};
+use crate::media_cache;
+use crate::media_toolkit::{CliToolkit, MediaToolkit, WEBM_VIDEO_ENCODERS};
+use crate::probe_cache::CachedToolkit;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter};
+
+// Subdirectory of the cache directory holding generated preview clips.
+const HOVER_PREVIEW_DIR_NAME: &str = "hover_previews";
+
+// A preview is typically 150-400 KB.
+const MAX_HOVER_PREVIEW_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+const PREVIEW_WIDTH: u32 = 320;
+
+// The clip is SAMPLE_COUNT short excerpts spread over the file, skipping the
+// first and last tenth where intros and credits usually sit.
+const SAMPLE_COUNT: usize = 4;
+const SAMPLE_SECS: f64 = 1.5;
+const SAMPLE_MARGIN_RATIO: f64 = 0.1;
+
+// Bumped by each `generate_hover_previews` call so an older batch stops
+// instead of competing with the one for the gallery now on screen.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct HoverPreviewUpdate {
+    path: String,
+    preview_path: Option<String>,
+}
+
+// Start times of the excerpts and the length of each. Files too short to
+// sample are used from the start, cut to the length of a full preview.
+fn sample_starts(duration: f64) -> (Vec<f64>, f64) {
+    let total = SAMPLE_COUNT as f64 * SAMPLE_SECS;
+    let usable = duration * (1.0 - 2.0 * SAMPLE_MARGIN_RATIO);
+    if usable < total * 2.0 {
+        return (vec![0.0], duration.min(total));
+    }
+
+    let first = duration * SAMPLE_MARGIN_RATIO;
+    let step = (usable - SAMPLE_SECS) / (SAMPLE_COUNT - 1) as f64;
+    let starts = (0..SAMPLE_COUNT)
+        .map(|sample| first + sample as f64 * step)
+        .collect();
+    (starts, SAMPLE_SECS)
+}
+
+// Animated WebP when the FFmpeg build has libwebp, WebM otherwise. Checked
+// once per batch so a build that can write neither fails up front instead of
+// on every file.
+async fn clip_extension() -> Result<&'static str, String> {
+    let caps = tokio::task::spawn_blocking(capabilities::get_capabilities)
+        .await
+        .map_err(|e| format!("Failed to check FFmpeg capabilities: {}", e))??;
+    if caps.has(Component::Encoder, "libwebp_anim") {
+        return Ok("webp");
+    }
+
+    // Any of the WebM encoders will do; the preferred one is reported if the
+    // build has none of them.
+    let encoder = WEBM_VIDEO_ENCODERS
+        .iter()
+        .map(|(name, _)| *name)
+        .find(|name| caps.has(Component::Encoder, name))
+        .unwrap_or(WEBM_VIDEO_ENCODERS[0].0);
+    capabilities::ensure_supported(
+        &[(Component::Encoder, encoder), (Component::Muxer, "webm")],
+        "make hover previews",
+    )
+    .await?;
+    Ok("webm")
+}
+
+pub(crate) async fn hover_preview<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+    extension: &str,
+) -> Result<String, String> {
+    let duration = toolkit.probe_duration(video_path).await?.max(0.0);
+    if duration == 0.0 {
+        return Err(format!("{} has no duration to preview", video_path));
+    }
+    let (starts, sample_secs) = sample_starts(duration);
+
+    let digest = media_cache::file_digest(
+        video_path,
+        &format!("{}x{}x{}", PREVIEW_WIDTH, starts.len(), sample_secs),
+    )?;
+    let target =
+        media_cache::dir(HOVER_PREVIEW_DIR_NAME)?.join(format!("{}.{}", digest, extension));
+
+    media_cache::get_or_create(
+        &target,
+        MAX_HOVER_PREVIEW_CACHE_BYTES,
+        |partial| async move {
+            toolkit
+                .render_hover_preview(video_path, &starts, sample_secs, PREVIEW_WIDTH, &partial)
+                .await
+        },
+    )
+    .await?;
+
+    Ok(target.to_string_lossy().to_string())
+}
+
+// Builds short silent looping clips for the gallery one file at a time at
+// background priority, emitting `hover-preview-ready` as each is done (with
+// no path if it failed). Meant to be called with the list from
+// `get_recent_videos`; clips already cached are reported straight away.
+// Fails without starting the batch if FFmpeg cannot write either format.
+#[tauri::command]
+pub(crate) async fn generate_hover_previews(
+    app_handle: AppHandle,
+    paths: Vec<String>,
+) -> Result<(), String> {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let extension = clip_extension().await?;
+
+    tokio::spawn(async move {
+        for path in paths {
+            if GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            if crate::is_cloud_only_path(&path) {
+                continue;
+            }
+
+            let preview_path = hover_preview(&CachedToolkit::new(CliToolkit), &path, extension)
+                .await
+                .inspect_err(|_e| {
+                    #[cfg(debug_assertions)]
+                    eprintln!("[Hover Preview] Failed for {}: {}", path, _e);
+                })
+                .ok();
+            let _ = app_handle.emit(
+                "hover-preview-ready",
+                HoverPreviewUpdate { path, preview_path },
+            );
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_files_are_sampled_inside_the_margins() {
+        let (starts, sample_secs) = sample_starts(100.0);
+        assert_eq!(sample_secs, SAMPLE_SECS);
+        assert_eq!(starts.len(), SAMPLE_COUNT);
+        assert_eq!(starts[0], 10.0);
+        assert!((starts[SAMPLE_COUNT - 1] + SAMPLE_SECS - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn samples_are_evenly_spaced() {
+        let (starts, _) = sample_starts(3600.0);
+        let step = starts[1] - starts[0];
+        for pair in starts.windows(2) {
+            assert!((pair[1] - pair[0] - step).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn short_files_play_from_the_start() {
+        assert_eq!(sample_starts(4.0), (vec![0.0], 4.0));
+        assert_eq!(sample_starts(14.0), (vec![0.0], 6.0));
+    }
+
+    #[test]
+    fn threshold_is_twice_the_preview_length_of_usable_time() {
+        // 15 s leaves exactly 12 s between the margins.
+        assert_eq!(sample_starts(15.0).0.len(), SAMPLE_COUNT);
+        assert_eq!(sample_starts(14.9).0.len(), 1);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::media_toolkit::{CliToolkit, MediaToolkit};
+use crate::probe_cache::CachedToolkit;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+// Decode errors kept per file. A badly damaged file can log one per frame.
+pub(crate) const MAX_DECODE_ERRORS: usize = 200;
+
+// Errors closer together than this are reported as one corrupt range.
+const RANGE_MERGE_GAP_SECS: f64 = 2.0;
+
+// Decoding that stops this close to the probed duration is not truncation:
+// container durations routinely overshoot the last frame slightly.
+const TRUNCATION_TOLERANCE_SECS: f64 = 2.0;
+const TRUNCATION_TOLERANCE_RATIO: f64 = 0.01;
+
+// Bumped by each `verify_media_file` call and by `cancel_media_verify`. A
+// check that is no longer the current one is dropped, which kills its FFmpeg
+// process, so leaving a file stops its verification.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// How often a running check looks at GENERATION.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DecodeError {
+    // Decode position when the error was logged. FFmpeg reports progress
+    // about twice a second, so this is accurate to roughly that.
+    pub(crate) time: f64,
+    pub(crate) message: String,
+}
+
+// Raw result of decoding a whole file to the null muxer.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct DecodeCheck {
+    pub(crate) errors: Vec<DecodeError>,
+    // Errors beyond MAX_DECODE_ERRORS, counted but not stored.
+    pub(crate) omitted_errors: usize,
+    // How far decoding got, in seconds.
+    pub(crate) decoded_seconds: f64,
+    // False when FFmpeg gave up partway through the file.
+    pub(crate) completed: bool,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum IntegrityStatus {
+    Clean,
+    // Decodes to the end, with errors the player can skip over.
+    Glitches,
+    Truncated,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct TimeRange {
+    start: f64,
+    end: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IntegrityReport {
+    status: IntegrityStatus,
+    duration: Option<f64>,
+    decoded_seconds: f64,
+    // Where usable data ends, when the file stops before its duration.
+    truncated_at: Option<f64>,
+    corrupt_ranges: Vec<TimeRange>,
+    errors: Vec<DecodeError>,
+    omitted_errors: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct VerifyProgress {
+    path: String,
+    // 0-100, or None when the duration is unknown.
+    progress: Option<f64>,
+    decoded_seconds: f64,
+}
+
+// Drops the `@ 0x55d0c8a2e4c0` context address FFmpeg puts in log prefixes,
+// so identical errors read identically.
+pub(crate) fn clean_error_line(line: &str) -> String {
+    let line = line.trim();
+    if let (Some(at), Some(close)) = (line.find(" @ 0x"), line.find("] ")) {
+        if line.starts_with('[') && at < close {
+            return format!("{}{}", &line[..at], &line[close..]);
+        }
+    }
+    line.to_string()
+}
+
+fn corrupt_ranges(errors: &[DecodeError]) -> Vec<TimeRange> {
+    let mut ranges: Vec<TimeRange> = Vec::new();
+    for error in errors {
+        match ranges.last_mut() {
+            Some(range) if error.time - range.end <= RANGE_MERGE_GAP_SECS => {
+                range.end = range.end.max(error.time);
+            }
+            _ => ranges.push(TimeRange {
+                start: error.time,
+                end: error.time,
+            }),
+        }
+    }
+    ranges
+}
+
+fn build_report(check: DecodeCheck, duration: Option<f64>) -> IntegrityReport {
+    let stopped_early = duration.is_some_and(|duration| {
+        let tolerance = TRUNCATION_TOLERANCE_SECS.max(duration * TRUNCATION_TOLERANCE_RATIO);
+        check.decoded_seconds < duration - tolerance
+    });
+    let truncated_at = (!check.completed || stopped_early).then_some(check.decoded_seconds);
+
+    let status = if truncated_at.is_some() {
+        IntegrityStatus::Truncated
+    } else if check.errors.is_empty() {
+        IntegrityStatus::Clean
+    } else {
+        IntegrityStatus::Glitches
+    };
+
+    IntegrityReport {
+        status,
+        duration,
+        decoded_seconds: check.decoded_seconds,
+        truncated_at,
+        corrupt_ranges: corrupt_ranges(&check.errors),
+        errors: check.errors,
+        omitted_errors: check.omitted_errors,
+    }
+}
+
+// Resolves once another verification has started or this one was cancelled.
+async fn superseded(generation: u64) {
+    while GENERATION.load(Ordering::SeqCst) == generation {
+        tokio::time::sleep(CANCEL_POLL_INTERVAL).await;
+    }
+}
+
+pub(crate) async fn verify<T: MediaToolkit>(
+    toolkit: &T,
+    app_handle: &AppHandle,
+    video_path: &str,
+) -> Result<IntegrityReport, String> {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let duration = toolkit.probe_duration(video_path).await.ok();
+
+    let decode = toolkit.decode_check(video_path, |decoded_seconds| {
+        let _ = app_handle.emit(
+            "media-verify-progress",
+            VerifyProgress {
+                path: video_path.to_string(),
+                progress: duration
+                    .filter(|d| *d > 0.0)
+                    .map(|d| (decoded_seconds / d * 100.0).min(100.0)),
+                decoded_seconds,
+            },
+        );
+    });
+    let check = tokio::select! {
+        check = decode => check?,
+        _ = superseded(generation) => {
+            #[cfg(debug_assertions)]
+            println!("[Verify] Cancelled: {}", video_path);
+            return Err("Verification was cancelled".to_string());
+        }
+    };
+
+    let report = build_report(check, duration);
+
+    #[cfg(debug_assertions)]
+    println!(
+        "[Verify] {}: {:?}, {} errors",
+        video_path,
+        report.status,
+        report.errors.len() + report.omitted_errors
+    );
+
+    Ok(report)
+}
+
+// Decodes the whole file to find corrupt or missing data. Runs in the
+// long-running process pool and emits `media-verify-progress` while it works;
+// results are kept in the probe cache until the file changes. Starting a new
+// verification cancels the previous one.
+#[tauri::command]
+pub(crate) async fn verify_media_file(
+    app_handle: AppHandle,
+    video_path: String,
+) -> Result<IntegrityReport, String> {
+    verify(&CachedToolkit::new(CliToolkit), &app_handle, &video_path).await
+}
+
+// Stops the running verification, e.g. when the player is closed.
+#[tauri::command]
+pub(crate) fn cancel_media_verify() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors_at(times: &[f64]) -> Vec<DecodeError> {
+        times
+            .iter()
+            .map(|&time| DecodeError {
+                time,
+                message: "[h264] error while decoding MB 1 2".to_string(),
+            })
+            .collect()
+    }
+
+    fn bounds(ranges: &[TimeRange]) -> Vec<(f64, f64)> {
+        ranges
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect()
+    }
+
+    #[test]
+    fn nearby_errors_merge_into_one_range() {
+        let ranges = corrupt_ranges(&errors_at(&[10.0, 11.0, 12.5, 30.0, 30.0, 45.0]));
+        assert_eq!(bounds(&ranges), [(10.0, 12.5), (30.0, 30.0), (45.0, 45.0)]);
+    }
+
+    #[test]
+    fn gap_of_exactly_the_merge_distance_still_merges() {
+        let ranges = corrupt_ranges(&errors_at(&[5.0, 5.0 + RANGE_MERGE_GAP_SECS]));
+        assert_eq!(bounds(&ranges), [(5.0, 7.0)]);
+        assert!(corrupt_ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn status_follows_errors_and_truncation() {
+        let check = |errors: &[f64], decoded_seconds, completed| DecodeCheck {
+            errors: errors_at(errors),
+            decoded_seconds,
+            completed,
+            ..DecodeCheck::default()
+        };
+
+        let clean = build_report(check(&[], 599.0, true), Some(600.0));
+        assert_eq!(clean.status, IntegrityStatus::Clean);
+
+        let glitches = build_report(check(&[20.0], 600.0, true), Some(600.0));
+        assert_eq!(glitches.status, IntegrityStatus::Glitches);
+        assert_eq!(glitches.truncated_at, None);
+
+        let short = build_report(check(&[], 300.0, true), Some(600.0));
+        assert_eq!(short.status, IntegrityStatus::Truncated);
+        assert_eq!(short.truncated_at, Some(300.0));
+
+        let aborted = build_report(check(&[], 300.0, false), None);
+        assert_eq!(aborted.status, IntegrityStatus::Truncated);
+    }
+
+    #[test]
+    fn context_addresses_are_removed_from_errors() {
+        assert_eq!(
+            clean_error_line("  [h264 @ 0x55d0c8a2e4c0] error while decoding MB 3 4"),
+            "[h264] error while decoding MB 3 4"
+        );
+        assert_eq!(clean_error_line("plain message"), "plain message");
+    }
+}
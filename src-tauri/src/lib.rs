@@ -1,7 +1,34 @@
+mod audio_metadata;
+mod backup;
+mod capabilities;
+mod chapters;
+#[cfg(test)]
+mod fake_toolkit;
 mod ffmpeg;
+mod ffmpeg_error;
+mod folder_settings;
+mod hover_preview;
+mod integrity;
+mod loudness;
+mod managed_ffmpeg;
+mod media_cache;
+mod media_info;
+mod media_toolkit;
+mod paths;
 mod pip_window;
-
+mod playability;
+mod probe_cache;
+mod process_scheduler;
+mod recovery;
+mod seek_preview;
+mod settings;
+mod thumbnails;
+
+use ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use media_toolkit::{CliToolkit, MediaToolkit, StreamKind};
 use pip_window::{enter_pip_mode, exit_pip_mode, save_pip_window_layout, settle_pip_window};
+use probe_cache::CachedToolkit;
+use process_scheduler::Priority;
 use serde::Serialize;
 use std::collections::VecDeque;
 use std::fs;
@@ -55,39 +82,7 @@ fn get_ffmpeg_command() -> Command {
 }
 
 fn get_ffprobe_command() -> Command {
-    let ffmpeg_path_info = ffmpeg::resolve_ffmpeg_path_info();
-    if let Some(ffmpeg_path) = ffmpeg_path_info.path {
-        let ffmpeg_path = std::path::Path::new(&ffmpeg_path);
-        let ffprobe_name = if cfg!(target_os = "windows") {
-            "ffprobe.exe"
-        } else {
-            "ffprobe"
-        };
-
-        if let Some(bin_dir) = ffmpeg_path.parent() {
-            let ffprobe_path = bin_dir.join(ffprobe_name);
-            if ffprobe_path.exists() {
-                return create_hidden_command(ffprobe_path.to_str().unwrap_or("ffprobe"));
-            }
-        }
-    }
-
-    #[cfg(target_os = "windows")]
-    {
-        if let Ok(app_data) = std::env::var("LOCALAPPDATA") {
-            let ffprobe_exe = std::path::Path::new(&app_data)
-                .join("glucose")
-                .join("resources")
-                .join("ffmpeg")
-                .join("bin")
-                .join("ffprobe.exe");
-            if ffprobe_exe.exists() {
-                return create_hidden_command(ffprobe_exe.to_str().unwrap_or("ffprobe"));
-            }
-        }
-    }
-
-    create_hidden_command("ffprobe")
+    create_hidden_command(&ffmpeg::resolve_ffprobe_path())
 }
 
 // Global state to store pending file paths
@@ -123,59 +118,16 @@ fn default_gallery_paths() -> Vec<String> {
 
 #[tauri::command]
 fn get_gallery_paths() -> Result<Vec<String>, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_file = home.join(".glucose").join("config.json");
-
-    if !config_file.exists() {
-        return Ok(default_gallery_paths());
-    }
-
-    let content =
-        fs::read_to_string(&config_file).map_err(|e| format!("Failed to read config: {}", e))?;
-    let config: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?;
-
-    if let Some(arr) = config.get("gallery_paths").and_then(|v| v.as_array()) {
-        let result: Vec<String> = arr
-            .iter()
-            .filter_map(|v| v.as_str().map(String::from))
-            .collect();
-        return Ok(result);
-    }
-
-    Ok(default_gallery_paths())
+    let settings = settings::load_settings()?;
+    Ok(settings.gallery_paths.unwrap_or_else(default_gallery_paths))
 }
 
 #[tauri::command]
 fn save_gallery_paths(paths: Vec<String>) -> Result<(), String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".glucose");
-    let config_file = config_dir.join("config.json");
-
-    fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
-
-    let _guard = CONFIG_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
-
-    let mut config: serde_json::Value = if config_file.exists() {
-        let content = fs::read_to_string(&config_file)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?
-    } else {
-        serde_json::json!({})
-    };
-
-    let config_object = config
-        .as_object_mut()
-        .ok_or_else(|| "Config root must be a JSON object".to_string())?;
-    config_object.insert("gallery_paths".to_string(), serde_json::json!(paths));
-
-    let content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-    let temp_file = config_file.with_extension("json.tmp");
-    fs::write(&temp_file, &content).map_err(|e| format!("Failed to write temp config: {}", e))?;
-    fs::rename(&temp_file, &config_file).map_err(|e| format!("Failed to replace config: {}", e))?;
-
+    settings::modify_settings(|settings| {
+        settings.gallery_paths = Some(paths);
+        Ok(())
+    })?;
     Ok(())
 }
 
@@ -286,6 +238,8 @@ fn frontend_ready(app_handle: tauri::AppHandle) -> Result<(), String> {
     #[cfg(debug_assertions)]
     println!("Frontend is ready to receive events");
 
+    recovery::frontend_ready(&app_handle);
+
     // Check if there are any pending files and emit them now
     let pending_files: Vec<String> = {
         let mut pending = PENDING_FILES.lock().unwrap();
@@ -372,26 +326,30 @@ fn find_subtitle_for_video(video_path: String) -> Result<Option<String>, String>
     Ok(None)
 }
 
+// Waits for a process slot (see process_scheduler) before spawning, and keeps
+// it until the child has exited or been killed.
 async fn run_with_timeout(
     cmd: std::process::Command,
     timeout: std::time::Duration,
     label: &str,
-) -> Result<std::process::Output, String> {
+    priority: Priority,
+) -> Result<std::process::Output, FfmpegError> {
+    let _permit = process_scheduler::acquire(priority).await?;
     let mut child = tokio::process::Command::from(cmd)
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
         .kill_on_drop(true)
         .spawn()
-        .map_err(|e| format!("Failed to spawn {}: {}", label, e))?;
+        .map_err(|e| FfmpegError::spawn_failed(label, e))?;
 
     let mut stdout = match child.stdout.take() {
         Some(s) => s,
         None => {
             let _ = child.kill().await;
             let _ = child.wait().await;
-            return Err(format!(
-                "run_with_timeout: Failed to capture stdout for {}",
-                label
+            return Err(FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("run_with_timeout: Failed to capture stdout for {}", label),
             ));
         }
     };
@@ -401,9 +359,9 @@ async fn run_with_timeout(
         None => {
             let _ = child.kill().await;
             let _ = child.wait().await;
-            return Err(format!(
-                "run_with_timeout: Failed to capture stderr for {}",
-                label
+            return Err(FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("run_with_timeout: Failed to capture stderr for {}", label),
             ));
         }
     };
@@ -419,13 +377,24 @@ async fn run_with_timeout(
 
         let status = match status_res {
             Ok(s) => s,
-            Err(e) => return Err(format!("Failed to wait for {}: {}", label, e)),
+            Err(e) => {
+                return Err(FfmpegError::new(
+                    FfmpegErrorKind::Other,
+                    format!("Failed to wait for {}: {}", label, e),
+                ))
+            }
         };
         if let Err(e) = out_res {
-            return Err(format!("Failed to read stdout for {}: {}", label, e));
+            return Err(FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("Failed to read stdout for {}: {}", label, e),
+            ));
         }
         if let Err(e) = err_res {
-            return Err(format!("Failed to read stderr for {}: {}", label, e));
+            return Err(FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("Failed to read stderr for {}: {}", label, e),
+            ));
         }
 
         Ok(std::process::Output {
@@ -446,11 +415,7 @@ async fn run_with_timeout(
         Err(_) => {
             let _ = child.kill().await;
             let _ = child.wait().await;
-            Err(format!(
-                "{} timed out after {} seconds",
-                label,
-                timeout.as_secs()
-            ))
+            Err(FfmpegError::timed_out(label, timeout))
         }
     }
 }
@@ -486,65 +451,33 @@ fn format_bytes(bytes: u64) -> String {
 #[tauri::command]
 async fn get_embedded_subtitle_tracks(
     video_path: String,
-) -> Result<Vec<EmbeddedSubtitleTrack>, String> {
-    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
-    const SUPPORTED: &[&str] = &["subrip", "ass", "ssa", "webvtt", "mov_text", "text"];
-
-    let mut cmd = get_ffprobe_command();
-    cmd.args([
-        "-v",
-        "error",
-        "-select_streams",
-        "s",
-        "-show_entries",
-        "stream=index,codec_name:stream_tags=language,title",
-        "-of",
-        "json",
-        &video_path,
-    ]);
-
-    let output = run_with_timeout(cmd, TIMEOUT, "ffprobe").await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("ffprobe failed: {}", stderr));
-    }
+) -> Result<Vec<EmbeddedSubtitleTrack>, FfmpegError> {
+    embedded_subtitle_tracks(&CachedToolkit::new(CliToolkit), &video_path).await
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: serde_json::Value = match serde_json::from_str(&stdout) {
-        Ok(v) => v,
-        Err(e) => {
-            return Err(format!(
-                "Failed to parse ffprobe JSON: {} (stdout: {})",
-                e, stdout
-            ))
-        }
-    };
+async fn embedded_subtitle_tracks<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+) -> Result<Vec<EmbeddedSubtitleTrack>, FfmpegError> {
+    const SUPPORTED: &[&str] = &["subrip", "ass", "ssa", "webvtt", "mov_text", "text"];
 
-    let streams = match parsed["streams"].as_array() {
-        Some(s) => s,
-        None => return Err("ffprobe JSON missing 'streams' array".to_string()),
-    };
+    let streams = toolkit
+        .probe_streams(video_path, StreamKind::Subtitle)
+        .await?;
 
     let mut tracks = Vec::new();
     for stream in streams {
-        let codec_name = stream["codec_name"]
-            .as_str()
-            .unwrap_or("unknown")
-            .to_string();
+        let codec_name = stream.codec_name.unwrap_or_else(|| "unknown".to_string());
 
         if !SUPPORTED.contains(&codec_name.as_str()) {
             continue;
         }
 
-        let Some(index) = stream["index"].as_i64() else {
-            continue;
-        };
         tracks.push(EmbeddedSubtitleTrack {
-            index,
+            index: stream.index,
             codec_name,
-            language: stream["tags"]["language"].as_str().map(|s| s.to_string()),
-            title: stream["tags"]["title"].as_str().map(|s| s.to_string()),
+            language: stream.language,
+            title: stream.title,
         });
     }
 
@@ -559,50 +492,34 @@ async fn get_embedded_subtitle_tracks(
 }
 
 // Extract a single subtitle stream from a video file and return its content as
-// an SRT string. FFmpeg handles codec conversion (e.g. ASS → SRT) automatically
-// when the output format is forced to `srt`.  Sending output to `pipe:1` means
-// no temp file is written to disk.
+// an SRT string.
 #[tauri::command]
 async fn extract_embedded_subtitle(
     video_path: String,
     stream_index: i64,
-) -> Result<String, String> {
+) -> Result<String, FfmpegError> {
+    embedded_subtitle(&CachedToolkit::new(CliToolkit), &video_path, stream_index).await
+}
+
+async fn embedded_subtitle<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+    stream_index: i64,
+) -> Result<String, FfmpegError> {
     if stream_index < 0 {
-        return Err(format!("Invalid stream index: {}", stream_index));
+        return Err(FfmpegError::new(
+            FfmpegErrorKind::UnsupportedMapping,
+            format!("Invalid stream index: {}", stream_index),
+        ));
     }
 
-    // Subtitle packets are interleaved throughout the container, so extraction
-    // reads the whole file — scale the timeout with size so large MKVs don't hit
-    // a premature cap. Floor 30 s, cap 30 min.
-    let timeout = size_scaled_timeout(&video_path, 30, 1800);
-
     #[cfg(debug_assertions)]
     println!(
         "Extracting embedded subtitle stream {} from: {}",
         stream_index, video_path
     );
 
-    let mut cmd = get_ffmpeg_command();
-    cmd.args([
-        "-v",
-        "error",
-        "-i",
-        &video_path,
-        "-map",
-        &format!("0:{}", stream_index),
-        "-f",
-        "srt",
-        "pipe:1",
-    ]);
-
-    let output = run_with_timeout(cmd, timeout, "ffmpeg").await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFmpeg failed to extract subtitle: {}", stderr));
-    }
-
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    toolkit.extract_subtitle(video_path, stream_index).await
 }
 
 #[derive(Serialize, Clone)]
@@ -672,8 +589,20 @@ struct SetupStatus {
     ffmpeg_installed: bool,
     ffmpeg_path: Option<String>,
     ffmpeg_is_custom: bool,
+    ffmpeg_version: Option<String>,
+    ffmpeg_license: Option<String>,
+    // True when the FFmpeg build is older than the minimum supported version;
+    // `ffmpeg_unsupported_features` then lists what will not work.
+    ffmpeg_degraded: bool,
+    ffmpeg_minimum_version: String,
+    ffmpeg_unsupported_features: Vec<String>,
+    // ffprobe is resolved separately; durations and track listings need it.
+    ffprobe_installed: bool,
+    ffprobe_path: Option<String>,
+    ffprobe_is_custom: bool,
     models_installed: Vec<String>,
     setup_completed: bool,
+    portable_mode: bool,
 }
 
 #[derive(Serialize, Clone)]
@@ -685,7 +614,7 @@ struct DownloadProgress {
 }
 
 #[derive(Serialize, serde::Deserialize, Clone)]
-struct WatchProgress {
+pub(crate) struct WatchProgress {
     path: String,
     current_time: f64,
     duration: f64,
@@ -726,64 +655,29 @@ struct EmbeddedAudioTrack {
 }
 
 #[tauri::command]
-async fn get_embedded_audio_tracks(video_path: String) -> Result<Vec<EmbeddedAudioTrack>, String> {
-    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
-
-    let mut cmd = get_ffprobe_command();
-    cmd.args([
-        "-v",
-        "error",
-        "-select_streams",
-        "a",
-        "-show_entries",
-        "stream=index,codec_name,channels:stream_tags=language,title:stream_disposition=default",
-        "-of",
-        "json",
-        &video_path,
-    ]);
-
-    let output = run_with_timeout(cmd, TIMEOUT, "ffprobe").await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("ffprobe failed: {}", stderr));
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: serde_json::Value = match serde_json::from_str(&stdout) {
-        Ok(v) => v,
-        Err(e) => {
-            return Err(format!(
-                "Failed to parse ffprobe JSON: {} (stdout: {})",
-                e, stdout
-            ))
-        }
-    };
+async fn get_embedded_audio_tracks(
+    video_path: String,
+) -> Result<Vec<EmbeddedAudioTrack>, FfmpegError> {
+    embedded_audio_tracks(&CachedToolkit::new(CliToolkit), &video_path).await
+}
 
-    let streams = match parsed["streams"].as_array() {
-        Some(s) => s,
-        None => return Err("ffprobe JSON missing 'streams' array".to_string()),
-    };
+async fn embedded_audio_tracks<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+) -> Result<Vec<EmbeddedAudioTrack>, FfmpegError> {
+    let streams = toolkit.probe_streams(video_path, StreamKind::Audio).await?;
 
-    let mut tracks = Vec::new();
-    for stream in streams {
-        let Some(index) = stream["index"].as_i64() else {
-            continue;
-        };
-        let codec_name = stream["codec_name"]
-            .as_str()
-            .unwrap_or("unknown")
-            .to_string();
-        let is_default = stream["disposition"]["default"].as_i64().unwrap_or(0) == 1;
-        tracks.push(EmbeddedAudioTrack {
-            index,
-            codec_name,
-            language: stream["tags"]["language"].as_str().map(|s| s.to_string()),
-            title: stream["tags"]["title"].as_str().map(|s| s.to_string()),
-            channels: stream["channels"].as_i64(),
-            is_default,
-        });
-    }
+    let tracks: Vec<EmbeddedAudioTrack> = streams
+        .into_iter()
+        .map(|stream| EmbeddedAudioTrack {
+            index: stream.index,
+            codec_name: stream.codec_name.unwrap_or_else(|| "unknown".to_string()),
+            language: stream.language,
+            title: stream.title,
+            channels: stream.channels,
+            is_default: stream.is_default,
+        })
+        .collect();
 
     #[cfg(debug_assertions)]
     println!(
@@ -801,38 +695,57 @@ async fn get_embedded_audio_tracks(video_path: String) -> Result<Vec<EmbeddedAud
 async fn remux_with_audio_track(
     video_path: String,
     audio_stream_index: i64,
-) -> Result<String, String> {
+) -> Result<String, FfmpegError> {
+    remux_audio(
+        &CachedToolkit::new(CliToolkit),
+        &video_path,
+        audio_stream_index,
+    )
+    .await
+}
+
+async fn remux_audio<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+    audio_stream_index: i64,
+) -> Result<String, FfmpegError> {
     if audio_stream_index < 0 {
-        return Err(format!(
-            "Invalid audio stream index: {}",
-            audio_stream_index
+        return Err(FfmpegError::new(
+            FfmpegErrorKind::UnsupportedMapping,
+            format!("Invalid audio stream index: {}", audio_stream_index),
         ));
     }
 
-    let audio_tracks = get_embedded_audio_tracks(video_path.clone()).await?;
+    let audio_tracks = embedded_audio_tracks(toolkit, video_path).await?;
     if !audio_tracks
         .iter()
         .any(|track| track.index == audio_stream_index)
     {
-        return Err(format!(
-            "Stream index {} is not a valid audio track for this video",
-            audio_stream_index
+        return Err(FfmpegError::new(
+            FfmpegErrorKind::UnsupportedMapping,
+            format!(
+                "Stream index {} is not a valid audio track for this video",
+                audio_stream_index
+            ),
         ));
     }
 
-    let temp_dir = std::env::temp_dir();
+    let temp_dir = paths::temp_dir();
 
     // Remux writes a full stream-copy of the file to the temp folder. Bail early
     // with a clear message if the temp filesystem can't hold a copy, rather than
     // failing partway through and leaving a giant half-written file on disk.
-    if let Ok(meta) = fs::metadata(&video_path) {
+    if let Ok(meta) = fs::metadata(video_path) {
         let needed = meta.len();
         if let Ok(available) = fs4::available_space(&temp_dir) {
             if available < needed {
-                return Err(format!(
-                    "Not enough free space to switch audio tracks. Need about {} in the temp folder but only {} is available.",
-                    format_bytes(needed),
-                    format_bytes(available)
+                return Err(FfmpegError::new(
+                    FfmpegErrorKind::NoSpace,
+                    format!(
+                        "Not enough free space to switch audio tracks. Need about {} in the temp folder but only {} is available.",
+                        format_bytes(needed),
+                        format_bytes(available)
+                    ),
                 ));
             }
         }
@@ -863,18 +776,22 @@ async fn remux_with_audio_track(
                 break;
             }
             Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
-            Err(e) => return Err(format!("Failed to create temporary file: {}", e)),
+            Err(e) => {
+                return Err(FfmpegError::new(
+                    FfmpegErrorKind::Other,
+                    format!("Failed to create temporary file: {}", e),
+                ))
+            }
         }
     }
 
-    let temp_path = temp_path_opt
-        .ok_or_else(|| "Failed to generate a unique temporary file path".to_string())?;
+    let temp_path = temp_path_opt.ok_or_else(|| {
+        FfmpegError::new(
+            FfmpegErrorKind::Other,
+            "Failed to generate a unique temporary file path",
+        )
+    })?;
     let temp_path_str = temp_path.to_string_lossy().to_string();
-    let out = temp_path_str.clone();
-
-    // Stream-copy reads the input and writes a near-equal-size output, so the old
-    // fixed 120 s cap starved multi-GB files. Scale with size. Floor 120 s, cap 1 h.
-    let timeout = size_scaled_timeout(&video_path, 120, 3600);
 
     #[cfg(debug_assertions)]
     println!(
@@ -882,42 +799,19 @@ async fn remux_with_audio_track(
         audio_stream_index, video_path, temp_path_str
     );
 
-    let mut cmd = get_ffmpeg_command();
-    cmd.args([
-        "-v",
-        "error",
-        "-i",
-        &video_path,
-        "-map",
-        "0:V?",
-        "-map",
-        &format!("0:{}", audio_stream_index),
-        "-c",
-        "copy",
-        "-y",
-        &temp_path_str,
-    ]);
-
-    let output_result = run_with_timeout(cmd, timeout, "ffmpeg").await;
-
-    match output_result {
-        Ok(output) => {
-            if !output.status.success() {
-                let stderr_str = String::from_utf8_lossy(&output.stderr);
-                let _ = tokio::fs::remove_file(&temp_path).await;
-                return Err(format!("FFmpeg remux failed: {}", stderr_str));
-            }
-        }
-        Err(e) => {
-            let _ = tokio::fs::remove_file(&temp_path).await;
-            return Err(e);
-        }
+    if let Err(e) = toolkit
+        .remux_audio_track(video_path, audio_stream_index, &temp_path_str)
+        .await
+    {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return Err(e);
     }
 
-    Ok(out)
+    Ok(temp_path_str)
 }
 
-// Delete a file that must reside in the system temp directory.
+// Delete a file that must reside in the app temp directory (the system temp
+// directory, or the portable data folder in portable mode).
 #[tauri::command]
 async fn delete_temp_file(path: String) -> Result<(), String> {
     let target = std::path::Path::new(&path);
@@ -928,12 +822,12 @@ async fn delete_temp_file(path: String) -> Result<(), String> {
         Err(e) => return Err(e.to_string()),
     };
 
-    let temp_dir = std::env::temp_dir()
+    let temp_dir = paths::temp_dir()
         .canonicalize()
         .map_err(|e| e.to_string())?;
 
     if !p.starts_with(&temp_dir) {
-        return Err("Only files inside the system temp directory may be deleted".to_string());
+        return Err("Only files inside the temp directory may be deleted".to_string());
     }
     let file_name = p
         .file_name()
@@ -952,30 +846,18 @@ async fn delete_temp_file(path: String) -> Result<(), String> {
 
 // Get video duration using FFmpeg
 // Note: Caller should verify ffprobe is available before calling this
-async fn get_video_duration(video_path: &str) -> Option<f64> {
-    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
-    let mut cmd = get_ffprobe_command();
-    cmd.args([
-        "-v",
-        "error",
-        "-show_entries",
-        "format=duration",
-        "-of",
-        "default=noprint_wrappers=1:nokey=1",
-        video_path,
-    ]);
-    let output = run_with_timeout(cmd, TIMEOUT, "ffprobe").await.ok()?;
-    if output.status.success() {
-        let duration_str = String::from_utf8_lossy(&output.stdout);
-        duration_str.trim().parse::<f64>().ok()
-    } else {
-        None
-    }
+async fn get_video_duration<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+) -> Result<f64, FfmpegError> {
+    toolkit.probe_duration(video_path).await
 }
 
 // Check if FFmpeg is installed
 #[tauri::command]
 fn check_ffmpeg_installed() -> Result<bool, String> {
+    // Explicit checks always re-probe so a freshly installed FFmpeg is found.
+    ffmpeg::invalidate_ffmpeg_cache();
     let path_info = ffmpeg::resolve_ffmpeg_path_info();
     if path_info.path.is_some() {
         if path_info.is_custom {
@@ -991,8 +873,9 @@ fn check_ffmpeg_installed() -> Result<bool, String> {
 }
 
 // Returns the candidate directories to search for Whisper model files, in priority order.
-// Windows checks AppData\Local\glucose\resources\models first, then home\.whisper\models.
-// Non-Windows checks only home\.whisper\models.
+// Windows checks AppData\Local\glucose\resources\models first. Every platform then checks
+// the app models directory (see paths::models_dir) and, unless GLUCOSE_HOME is set, the
+// legacy home\.whisper\models folder.
 fn model_candidate_dirs() -> Vec<std::path::PathBuf> {
     let mut dirs = Vec::new();
 
@@ -1006,8 +889,16 @@ fn model_candidate_dirs() -> Vec<std::path::PathBuf> {
         );
     }
 
-    if let Some(home) = dirs::home_dir() {
-        dirs.push(home.join(".whisper").join("models"));
+    if let Ok(models_dir) = paths::models_dir() {
+        dirs.push(models_dir);
+    }
+
+    if paths::search_legacy_models() {
+        if let Some(legacy) = paths::legacy_models_dir() {
+            if !dirs.contains(&legacy) {
+                dirs.push(legacy);
+            }
+        }
     }
 
     dirs
@@ -1015,7 +906,7 @@ fn model_candidate_dirs() -> Vec<std::path::PathBuf> {
 
 // Check which Whisper models are installed
 #[tauri::command]
-fn check_installed_models() -> Result<Vec<String>, String> {
+pub(crate) fn check_installed_models() -> Result<Vec<String>, String> {
     let mut models = Vec::new();
     let model_files = vec![
         ("ggml-tiny.bin", "tiny"),
@@ -1050,6 +941,8 @@ fn check_installed_models() -> Result<Vec<String>, String> {
 // Get setup status
 #[tauri::command]
 fn get_setup_status() -> Result<SetupStatus, String> {
+    // Explicit checks always re-probe so a freshly installed FFmpeg is found.
+    ffmpeg::invalidate_ffmpeg_cache();
     let ffmpeg_path_info = ffmpeg::resolve_ffmpeg_path_info();
     let ffmpeg_installed = ffmpeg_path_info.path.is_some();
     if let Some(path) = &ffmpeg_path_info.path {
@@ -1058,9 +951,36 @@ fn get_setup_status() -> Result<SetupStatus, String> {
         } else {
             println!("[FFmpeg Check] ✓ Found FFmpeg at: {}", path);
         }
+        println!(
+            "[FFmpeg Check] Version: {} ({})",
+            ffmpeg_path_info.version.as_deref().unwrap_or("unknown"),
+            ffmpeg_path_info
+                .license
+                .as_deref()
+                .unwrap_or("unknown license")
+        );
     } else {
         println!("[FFmpeg Check] ✗ FFmpeg not found");
     }
+    let ffmpeg_degraded = ffmpeg_path_info.is_degraded();
+    let ffmpeg_unsupported_features = ffmpeg_path_info.unsupported_features();
+    if ffmpeg_degraded {
+        println!(
+            "[FFmpeg Check] ⚠ FFmpeg is older than {}; unavailable: {}",
+            ffmpeg::min_ffmpeg_version(),
+            ffmpeg_unsupported_features.join(", ")
+        );
+    }
+    let ffprobe_path_info = ffmpeg::resolve_ffprobe_path_info();
+    if let Some(path) = &ffprobe_path_info.path {
+        if ffprobe_path_info.is_custom {
+            println!("[FFmpeg Check] ✓ Found FFprobe at custom path: {}", path);
+        } else {
+            println!("[FFmpeg Check] ✓ Found FFprobe at: {}", path);
+        }
+    } else {
+        println!("[FFmpeg Check] ✗ FFprobe not found");
+    }
     let models_installed = check_installed_models()?;
 
     // Check if setup was completed (stored in config)
@@ -1070,8 +990,17 @@ fn get_setup_status() -> Result<SetupStatus, String> {
         ffmpeg_installed,
         ffmpeg_path: ffmpeg_path_info.path,
         ffmpeg_is_custom: ffmpeg_path_info.is_custom,
+        ffmpeg_version: ffmpeg_path_info.version,
+        ffmpeg_license: ffmpeg_path_info.license,
+        ffmpeg_degraded,
+        ffmpeg_minimum_version: ffmpeg::min_ffmpeg_version(),
+        ffmpeg_unsupported_features,
+        ffprobe_installed: ffprobe_path_info.path.is_some(),
+        ffprobe_path: ffprobe_path_info.path,
+        ffprobe_is_custom: ffprobe_path_info.is_custom,
         models_installed,
         setup_completed,
+        portable_mode: paths::portable_dir().is_some(),
     })
 }
 
@@ -1083,56 +1012,15 @@ fn mark_setup_completed() -> Result<(), String> {
 
 // Load setup completion status from config
 fn load_setup_completed() -> Result<bool, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".glucose");
-    let config_file = config_dir.join("config.json");
-
-    if !config_file.exists() {
-        return Ok(false);
-    }
-
-    let content =
-        fs::read_to_string(config_file).map_err(|e| format!("Failed to read config: {}", e))?;
-
-    let config: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?;
-
-    Ok(config
-        .get("setup_completed")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false))
+    Ok(settings::load_settings()?.setup_completed)
 }
 
 // Save setup completion status to config
 fn save_setup_completed(completed: bool) -> Result<(), String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".glucose");
-    let config_file = config_dir.join("config.json");
-
-    fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
-
-    let _guard = CONFIG_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
-
-    let mut config: serde_json::Value = if config_file.exists() {
-        let content = fs::read_to_string(&config_file)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?
-    } else {
-        serde_json::json!({})
-    };
-
-    let config_object = config
-        .as_object_mut()
-        .ok_or_else(|| "Config root must be a JSON object".to_string())?;
-    config_object.insert("setup_completed".to_string(), serde_json::json!(completed));
-
-    let content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-    let temp_file = config_file.with_extension("json.tmp");
-    fs::write(&temp_file, &content).map_err(|e| format!("Failed to write temp config: {}", e))?;
-    fs::rename(&temp_file, &config_file).map_err(|e| format!("Failed to replace config: {}", e))?;
-
+    settings::modify_settings(|settings| {
+        settings.setup_completed = completed;
+        Ok(())
+    })?;
     Ok(())
 }
 
@@ -1154,8 +1042,7 @@ async fn download_whisper_model(
         model_name
     );
 
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let models_dir = home.join(".whisper").join("models");
+    let models_dir = paths::models_dir()?;
 
     // Create models directory
     fs::create_dir_all(&models_dir)
@@ -1176,7 +1063,7 @@ async fn download_whisper_model(
 }
 
 // Download file with progress reporting
-async fn download_file_with_progress(
+pub(crate) async fn download_file_with_progress(
     app_handle: &tauri::AppHandle,
     url: &str,
     output_path: &std::path::PathBuf,
@@ -1242,29 +1129,38 @@ async fn download_file_with_progress(
             .map_err(|e| format!("Failed to write to file: {}", e))?;
 
         downloaded += chunk.len() as u64;
-        let percentage = if total_size > 0 {
-            (downloaded as f32 / total_size as f32) * 100.0
-        } else {
-            0.0
-        };
 
         // Emit progress every 1MB or so
         if downloaded % (1024 * 1024) < chunk.len() as u64 || downloaded == total_size {
-            let _ = app_handle.emit(
-                "download-progress",
-                DownloadProgress {
-                    downloaded,
-                    total: total_size,
-                    percentage,
-                    message: message.to_string(),
-                },
-            );
+            emit_download_progress(app_handle, downloaded, total_size, message);
         }
     }
 
     Ok(())
 }
 
+pub(crate) fn emit_download_progress(
+    app_handle: &tauri::AppHandle,
+    downloaded: u64,
+    total: u64,
+    message: &str,
+) {
+    let percentage = if total > 0 {
+        (downloaded as f32 / total as f32) * 100.0
+    } else {
+        0.0
+    };
+    let _ = app_handle.emit(
+        "download-progress",
+        DownloadProgress {
+            downloaded,
+            total,
+            percentage,
+            message: message.to_string(),
+        },
+    );
+}
+
 // Find an installed Whisper model file across all candidate directories.
 // Returns None if the model is not present in any location.
 fn find_model_path(model_name: &str) -> Option<std::path::PathBuf> {
@@ -1278,63 +1174,26 @@ fn find_model_path(model_name: &str) -> Option<std::path::PathBuf> {
 // Returns Some(error) only when ffprobe ran successfully and confirmed no audio track.
 // Returns None when ffprobe is unavailable or errored — callers should proceed and
 // let FFmpeg surface the real failure rather than blocking on a missing preflight tool.
-async fn check_video_has_audio(video_path: &str) -> Option<String> {
-    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
-    let mut cmd = get_ffprobe_command();
-    cmd.args([
-        "-v",
-        "error",
-        "-select_streams",
-        "a:0",
-        "-show_entries",
-        "stream=codec_type",
-        "-of",
-        "default=noprint_wrappers=1",
-        video_path,
-    ]);
-    let output = match run_with_timeout(cmd, TIMEOUT, "ffprobe").await {
-        Ok(o) => o,
-        Err(_) => return None, // ffprobe not available — skip the check
-    };
-    if !output.status.success() {
-        return None; // ffprobe errored (e.g. unreadable file) — let FFmpeg handle it
-    }
-    if String::from_utf8_lossy(&output.stdout).trim().is_empty() {
-        return Some(
+async fn check_video_has_audio<T: MediaToolkit>(toolkit: &T, video_path: &str) -> Option<String> {
+    match toolkit.probe_streams(video_path, StreamKind::Audio).await {
+        Ok(streams) if streams.is_empty() => Some(
             "This video has no audio track. AI subtitle generation requires audio.".to_string(),
-        );
+        ),
+        // ffprobe missing or errored (e.g. unreadable file) — let FFmpeg handle it
+        _ => None,
     }
-    None
 }
 
 // Helper function to extract audio from video using FFmpeg
-async fn extract_audio_from_video(video_path: &str, output_audio_path: &str) -> Result<(), String> {
+async fn extract_audio_from_video<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+    output_audio_path: &str,
+) -> Result<(), String> {
     #[cfg(debug_assertions)]
     println!("Extracting audio from video: {}", video_path);
 
-    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600); // 10 minutes
-
-    let mut cmd = get_ffmpeg_command();
-    cmd.args([
-        "-i",
-        video_path,
-        "-vn", // No video
-        "-acodec",
-        "pcm_s16le", // PCM 16-bit little-endian
-        "-ar",
-        "16000", // Sample rate 16kHz (Whisper's expected rate)
-        "-ac",
-        "1",  // Mono channel
-        "-y", // Overwrite output file
-        output_audio_path,
-    ]);
-
-    let output = run_with_timeout(cmd, TIMEOUT, "ffmpeg").await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFmpeg failed to extract audio: {}", stderr));
-    }
+    toolkit.extract_audio(video_path, output_audio_path).await?;
 
     #[cfg(debug_assertions)]
     println!("Audio extracted successfully to: {}", output_audio_path);
@@ -1380,9 +1239,19 @@ fn generate_srt_from_segments(
 async fn generate_subtitles(
     app_handle: tauri::AppHandle,
     video_path: String,
-    model_size: String,
-    language: String,
+    model_size: Option<String>,
+    language: Option<String>,
 ) -> Result<String, String> {
+    // Anything the caller leaves unset comes from the folder's `.glucose.json`
+    // or the global defaults.
+    let preferences = folder_settings::resolve_media_preferences(&video_path).preferences;
+    let model_size = model_size
+        .or(preferences.whisper_model)
+        .unwrap_or_else(|| "tiny".to_string());
+    let language = language
+        .or(preferences.whisper_language)
+        .unwrap_or_else(|| "en".to_string());
+
     #[cfg(debug_assertions)]
     {
         println!("Starting subtitle generation for: {}", video_path);
@@ -1412,7 +1281,7 @@ async fn generate_subtitles(
 
     // Check the video has an audio track before creating any temp files.
     // Skipped gracefully if ffprobe is unavailable — FFmpeg will surface the failure instead.
-    if let Some(e) = check_video_has_audio(&video_path).await {
+    if let Some(e) = check_video_has_audio(&CachedToolkit::new(CliToolkit), &video_path).await {
         let _ = app_handle.emit(
             "subtitle-generation-progress",
             SubtitleGenerationProgress {
@@ -1428,9 +1297,9 @@ async fn generate_subtitles(
     let subtitle_path = video_dir.join(format!("{}.srt", video_stem.to_string_lossy()));
     let subtitle_path_str = subtitle_path.to_string_lossy().to_string();
 
-    // Create a uniquely named temp audio file in the system temp directory,
+    // Create a uniquely named temp audio file in the app temp directory,
     // using the same pid+nanos+create_new pattern as remux_with_audio_track.
-    let temp_dir = std::env::temp_dir();
+    let temp_dir = paths::temp_dir();
     let mut temp_audio_path_opt: Option<std::path::PathBuf> = None;
     for i in 0..100u128 {
         let nanos = std::time::SystemTime::now()
@@ -1469,7 +1338,7 @@ async fn generate_subtitles(
         },
     );
 
-    extract_audio_from_video(&video_path, &temp_audio_str)
+    extract_audio_from_video(&CliToolkit, &video_path, &temp_audio_str)
         .await
         .map_err(|e| {
             let _ = fs::remove_file(&temp_audio_str);
@@ -1748,40 +1617,62 @@ fn read_wav_file(path: &str) -> Result<Vec<f32>, String> {
 
 static WATCH_PROGRESS_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
 
-fn watch_progress_lock() -> &'static Mutex<()> {
+pub(crate) fn watch_progress_lock() -> &'static Mutex<()> {
     WATCH_PROGRESS_LOCK.get_or_init(|| Mutex::new(()))
 }
 
-fn watch_progress_path() -> Result<std::path::PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    Ok(home.join(".glucose").join("watch_progress.json"))
+pub(crate) fn watch_progress_path() -> Result<std::path::PathBuf, String> {
+    Ok(paths::state_dir()?.join("watch_progress.json"))
+}
+
+fn parse_watch_progress(
+    content: &str,
+) -> Result<std::collections::HashMap<String, WatchProgress>, String> {
+    serde_json::from_str(content).map_err(|e| format!("Failed to parse progress file: {}", e))
 }
 
-fn read_watch_progress(
+// Callers must hold the exclusive file lock: a corrupt file is quarantined and
+// replaced by its newest good backup (or an empty history) so one bad write
+// cannot break progress for the session.
+pub(crate) fn read_watch_progress(
     progress_file: &std::path::Path,
 ) -> Result<std::collections::HashMap<String, WatchProgress>, String> {
-    match fs::read_to_string(progress_file) {
-        Ok(content) => serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse progress file: {}", e)),
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            Ok(std::collections::HashMap::new())
+    let progress = recovery::read_with_recovery(progress_file, parse_watch_progress)?;
+    Ok(progress.unwrap_or_default())
+}
+
+// Reads watch progress under the shared file lock, which is all a plain read
+// needs. Repairing a corrupt file writes, so that case drops the shared lock
+// and goes through `read_watch_progress` under the exclusive one.
+pub(crate) fn read_watch_progress_shared(
+    progress_file: &std::path::Path,
+) -> Result<std::collections::HashMap<String, WatchProgress>, String> {
+    {
+        let _file_lock = acquire_watch_progress_file_lock_shared(progress_file)?;
+        match fs::read_to_string(progress_file) {
+            Ok(content) => {
+                if let Ok(progress) = parse_watch_progress(&content) {
+                    return Ok(progress);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(std::collections::HashMap::new());
+            }
+            Err(e) => return Err(format!("Failed to read progress file: {}", e)),
         }
-        Err(e) => Err(format!("Failed to read progress file: {}", e)),
     }
+
+    let _file_lock = acquire_watch_progress_file_lock_exclusive(progress_file)?;
+    read_watch_progress(progress_file)
 }
 
-fn write_watch_progress(
+pub(crate) fn write_watch_progress(
     progress_file: &std::path::Path,
     map: &std::collections::HashMap<String, WatchProgress>,
 ) -> Result<(), String> {
     let content = serde_json::to_string_pretty(map)
         .map_err(|e| format!("Failed to serialize progress: {}", e))?;
-    let temp_file = progress_file.with_extension("json.tmp");
-    fs::write(&temp_file, &content)
-        .map_err(|e| format!("Failed to write temp progress file: {}", e))?;
-    fs::rename(&temp_file, progress_file)
-        .map_err(|e| format!("Failed to replace progress file: {}", e))?;
-    Ok(())
+    recovery::write_with_backups(progress_file, &content)
 }
 
 fn open_progress_lock_file(progress_file: &std::path::Path, read: bool) -> Result<fs::File, String> {
@@ -1797,7 +1688,7 @@ fn open_progress_lock_file(progress_file: &std::path::Path, read: bool) -> Resul
         .map_err(|e| format!("Failed to open progress lock file: {}", e))
 }
 
-fn acquire_watch_progress_file_lock_exclusive(progress_file: &std::path::Path) -> Result<fs::File, String> {
+pub(crate) fn acquire_watch_progress_file_lock_exclusive(progress_file: &std::path::Path) -> Result<fs::File, String> {
     let f = open_progress_lock_file(progress_file, false)?;
     FileExt::lock(&f)
         .map_err(|e| format!("Failed to acquire exclusive progress file lock: {}", e))?;
@@ -1847,16 +1738,13 @@ fn get_watch_progress(video_path: String) -> Result<Option<WatchProgress>, Strin
     let _guard = watch_progress_lock()
         .lock()
         .map_err(|_| "Watch progress lock poisoned".to_string())?;
-    let _file_lock = acquire_watch_progress_file_lock_shared(&progress_file)?;
-    let progress_map = read_watch_progress(&progress_file)?;
+    let progress_map = read_watch_progress_shared(&progress_file)?;
     Ok(progress_map.get(&video_path).cloned())
 }
 
 // Get video file info
 #[tauri::command]
 async fn get_video_info(video_path: String) -> Result<VideoInfo, String> {
-    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
-
     let path = Path::new(&video_path);
 
     let metadata = fs::metadata(path).map_err(|e| format!("Failed to get file metadata: {}", e))?;
@@ -1869,36 +1757,11 @@ async fn get_video_info(video_path: String) -> Result<VideoInfo, String> {
         .unwrap_or("unknown")
         .to_uppercase();
 
-    let video_codec = {
-        let mut cmd = get_ffprobe_command();
-        cmd.args([
-            "-v",
-            "error",
-            "-select_streams",
-            "v:0",
-            "-show_entries",
-            "stream=codec_name",
-            "-of",
-            "json",
-            &video_path,
-        ]);
-        match run_with_timeout(cmd, TIMEOUT, "ffprobe").await {
-            Ok(output) if output.status.success() => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                serde_json::from_str::<serde_json::Value>(&stdout)
-                    .ok()
-                    .and_then(|v| {
-                        v["streams"]
-                            .as_array()?
-                            .first()?
-                            .get("codec_name")?
-                            .as_str()
-                            .map(str::to_string)
-                    })
-            }
-            _ => None,
-        }
-    };
+    let video_codec = CachedToolkit::new(CliToolkit)
+        .probe_streams(&video_path, StreamKind::Video)
+        .await
+        .ok()
+        .and_then(|streams| streams.into_iter().next()?.codec_name);
 
     Ok(VideoInfo {
         format,
@@ -1925,14 +1788,11 @@ async fn convert_video(
     app_handle: tauri::AppHandle,
     video_path: String,
     target_format: String,
-) -> Result<String, String> {
+) -> Result<String, FfmpegError> {
+    let invalid_path = || FfmpegError::new(FfmpegErrorKind::FileNotFound, "Invalid video path");
     let video_path_obj = Path::new(&video_path);
-    let video_dir = video_path_obj
-        .parent()
-        .ok_or("Could not get video directory")?;
-    let video_stem = video_path_obj
-        .file_stem()
-        .ok_or("Could not get video filename")?;
+    let video_dir = video_path_obj.parent().ok_or_else(invalid_path)?;
+    let video_stem = video_path_obj.file_stem().ok_or_else(invalid_path)?;
 
     // Output path
     let output_path = video_dir.join(format!(
@@ -1952,95 +1812,30 @@ async fn convert_video(
         },
     );
 
-    // Run conversion in blocking task
-    let video_path_clone = video_path.clone();
-    let output_path_clone = output_path_str.clone();
-    let target_format_clone = target_format.clone();
-    let app_handle_clone = app_handle.clone();
-
-    tokio::task::spawn_blocking(move || {
-        convert_video_with_ffmpeg(
-            &video_path_clone,
-            &output_path_clone,
-            &target_format_clone,
-            &app_handle_clone,
-        )
-    })
-    .await
-    .map_err(|e| format!("Conversion task failed: {}", e))??;
-
-    // Emit completion
     let _ = app_handle.emit(
         "conversion-progress",
         ConversionProgress {
-            stage: "complete".to_string(),
-            progress: 100.0,
-            message: "Conversion complete!".to_string(),
+            stage: "converting".to_string(),
+            progress: 50.0,
+            message: format!("Converting to {}...", target_format.to_uppercase()),
         },
     );
 
-    Ok(output_path_str)
-}
+    CliToolkit
+        .transcode(&video_path, &output_path_str, &target_format)
+        .await?;
 
-// Convert video using FFmpeg
-fn convert_video_with_ffmpeg(
-    input_path: &str,
-    output_path: &str,
-    target_format: &str,
-    app_handle: &tauri::AppHandle,
-) -> Result<(), String> {
+    // Emit completion
     let _ = app_handle.emit(
         "conversion-progress",
         ConversionProgress {
-            stage: "converting".to_string(),
-            progress: 50.0,
-            message: format!("Converting to {}...", target_format.to_uppercase()),
+            stage: "complete".to_string(),
+            progress: 100.0,
+            message: "Conversion complete!".to_string(),
         },
     );
 
-    // Build FFmpeg command based on target format
-    let mut cmd = get_ffmpeg_command();
-    cmd.arg("-i").arg(input_path);
-
-    match target_format {
-        "mp4" => {
-            cmd.args([
-                "-c:v", "libx264", "-preset", "medium", "-crf", "23", "-c:a", "aac", "-b:a", "192k",
-            ]);
-        }
-        "webm" => {
-            cmd.args([
-                "-c:v",
-                "libvpx-vp9",
-                "-crf",
-                "30",
-                "-b:v",
-                "0",
-                "-c:a",
-                "libopus",
-            ]);
-        }
-        "mkv" => {
-            cmd.args(["-c:v", "copy", "-c:a", "copy"]); // Just remux, no re-encoding
-        }
-        _ => return Err(format!("Unsupported format: {}", target_format)),
-    }
-
-    cmd.arg("-y").arg(output_path);
-
-    let output = cmd.output().map_err(|e| {
-        format!(
-            "Failed to execute ffmpeg: {}. Make sure FFmpeg is installed.",
-            e
-        )
-    })?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFmpeg conversion failed: {}", stderr));
-    }
-
-    Ok(())
+    Ok(output_path_str)
 }
 
 // Get all watch progress data
@@ -2050,8 +1845,7 @@ fn get_all_watch_progress() -> Result<std::collections::HashMap<String, WatchPro
     let _guard = watch_progress_lock()
         .lock()
         .map_err(|_| "Watch progress lock poisoned".to_string())?;
-    let _file_lock = acquire_watch_progress_file_lock_shared(&progress_file)?;
-    read_watch_progress(&progress_file)
+    read_watch_progress_shared(&progress_file)
 }
 
 #[tauri::command]
@@ -2193,17 +1987,7 @@ async fn fetch_video_durations(
     paths: Vec<String>,
 ) -> Result<(), String> {
     tokio::spawn(async move {
-        let ffprobe_available = tokio::task::spawn_blocking(|| {
-            get_ffprobe_command()
-                .arg("-version")
-                .output()
-                .map(|o| o.status.success())
-                .unwrap_or(false)
-        })
-        .await
-        .unwrap_or(false);
-
-        if !ffprobe_available {
+        if !CliToolkit.is_available().await {
             let _ = app_handle.emit("ffprobe-unavailable", ());
             return;
         }
@@ -2212,7 +1996,15 @@ async fn fetch_video_durations(
             let duration = if is_cloud_only_path(&path) {
                 None
             } else {
-                get_video_duration(&path).await
+                match get_video_duration(&CachedToolkit::new(CliToolkit), &path).await {
+                    Ok(duration) => Some(duration),
+                    // ffprobe went away mid-scan; every remaining file would fail the same way.
+                    Err(e) if e.kind == FfmpegErrorKind::NotInstalled => {
+                        let _ = app_handle.emit("ffprobe-unavailable", ());
+                        return;
+                    }
+                    Err(_) => None,
+                }
             };
             let _ = app_handle.emit(
                 "video-duration-ready",
@@ -2340,6 +2132,9 @@ pub fn run() {
 
     builder
         .setup(|app| {
+            paths::migrate_legacy_data();
+            settings::start_config_watcher(app.handle().clone());
+
             // Handle command line arguments for file associations
             let args: Vec<String> = std::env::args().collect();
 
@@ -2425,8 +2220,31 @@ pub fn run() {
             save_pip_window_layout,
             settle_pip_window,
             ffmpeg::get_ffmpeg_path,
+            ffmpeg::refresh_ffmpeg_path,
+            capabilities::get_ffmpeg_capabilities,
             ffmpeg::pick_ffmpeg_executable,
-            ffmpeg::save_ffmpeg_custom_path
+            ffmpeg::save_ffmpeg_custom_path,
+            ffmpeg::get_ffprobe_path,
+            ffmpeg::save_ffprobe_custom_path,
+            managed_ffmpeg::install_managed_ffmpeg,
+            process_scheduler::get_process_queue_status,
+            media_info::get_media_info,
+            chapters::get_chapters,
+            playability::check_playability,
+            playability::fix_playability,
+            audio_metadata::get_audio_metadata,
+            integrity::cancel_media_verify,
+            integrity::verify_media_file,
+            loudness::get_loudness_gain,
+            loudness::analyze_loudness,
+            thumbnails::get_thumbnail,
+            seek_preview::get_seek_preview,
+            hover_preview::generate_hover_previews,
+            settings::get_settings,
+            settings::update_settings,
+            backup::export_user_data,
+            backup::import_user_data,
+            folder_settings::get_effective_settings
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
@@ -2486,3 +2304,158 @@ pub fn run() {
             }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake_toolkit::FakeToolkit;
+    use ffmpeg_error::FfmpegErrorKind;
+    use media_toolkit::ProbedStream;
+
+    const VIDEO: &str = "/videos/film.mkv";
+
+    fn stream(index: i64, codec_name: &str, language: &str) -> ProbedStream {
+        ProbedStream {
+            index,
+            codec_name: Some(codec_name.to_string()),
+            language: Some(language.to_string()),
+            ..ProbedStream::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn subtitle_listing_skips_bitmap_formats() {
+        let toolkit = FakeToolkit::new().with_streams(
+            VIDEO,
+            StreamKind::Subtitle,
+            vec![
+                stream(2, "subrip", "eng"),
+                stream(3, "hdmv_pgs_subtitle", "eng"),
+                stream(4, "ass", "jpn"),
+            ],
+        );
+
+        let tracks = embedded_subtitle_tracks(&toolkit, VIDEO).await.unwrap();
+        let indexes: Vec<i64> = tracks.iter().map(|t| t.index).collect();
+        assert_eq!(indexes, [2, 4]);
+        assert_eq!(tracks[1].language.as_deref(), Some("jpn"));
+    }
+
+    #[tokio::test]
+    async fn audio_listing_keeps_stream_details() {
+        let toolkit = FakeToolkit::new().with_streams(
+            VIDEO,
+            StreamKind::Audio,
+            vec![ProbedStream {
+                channels: Some(6),
+                is_default: true,
+                ..stream(1, "ac3", "eng")
+            }],
+        );
+
+        let tracks = embedded_audio_tracks(&toolkit, VIDEO).await.unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].codec_name, "ac3");
+        assert_eq!(tracks[0].channels, Some(6));
+        assert!(tracks[0].is_default);
+    }
+
+    #[tokio::test]
+    async fn track_listing_reports_probe_failure() {
+        let toolkit = FakeToolkit::new();
+        let Err(err) = embedded_audio_tracks(&toolkit, VIDEO).await else {
+            panic!("listing an unreadable file succeeded");
+        };
+        assert_eq!(err.kind, FfmpegErrorKind::FileNotFound);
+        assert!(err.to_string().contains("No such file"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn subtitle_extraction_returns_the_stream() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+        let toolkit = FakeToolkit::new().with_subtitle(VIDEO, 2, srt);
+        assert_eq!(embedded_subtitle(&toolkit, VIDEO, 2).await.unwrap(), srt);
+    }
+
+    #[tokio::test]
+    async fn subtitle_extraction_rejects_negative_index_without_running_ffmpeg() {
+        let toolkit = FakeToolkit::new();
+        assert!(embedded_subtitle(&toolkit, VIDEO, -1).await.is_err());
+        assert!(toolkit.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_subtitle_stream_is_an_error() {
+        let toolkit = FakeToolkit::new().with_subtitle(VIDEO, 2, "");
+        let err = embedded_subtitle(&toolkit, VIDEO, 5).await.unwrap_err();
+        assert_eq!(err.kind, FfmpegErrorKind::UnsupportedMapping);
+        assert!(err.to_string().contains("stream 5 not found"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn remux_rejects_stream_that_is_not_an_audio_track() {
+        let toolkit = FakeToolkit::new().with_streams(
+            VIDEO,
+            StreamKind::Audio,
+            vec![stream(1, "aac", "eng")],
+        );
+
+        let err = remux_audio(&toolkit, VIDEO, 3).await.unwrap_err();
+        assert_eq!(err.kind, FfmpegErrorKind::UnsupportedMapping);
+        assert!(
+            err.to_string().contains("not a valid audio track"),
+            "{}",
+            err
+        );
+        assert_eq!(toolkit.calls(), [format!("probe_streams {} Audio", VIDEO)]);
+    }
+
+    #[tokio::test]
+    async fn failed_remux_removes_its_temp_file() {
+        let toolkit = FakeToolkit::new()
+            .with_streams(VIDEO, StreamKind::Audio, vec![stream(1, "aac", "eng")])
+            .failing(
+                "remux_audio_track",
+                FfmpegErrorKind::NoSpace,
+                "FFmpeg remux failed",
+            );
+
+        let err = remux_audio(&toolkit, VIDEO, 1).await.unwrap_err();
+        assert_eq!(err.kind, FfmpegErrorKind::NoSpace);
+
+        let calls = toolkit.calls();
+        let output = calls[1].rsplit(' ').next().unwrap();
+        assert!(!Path::new(output).exists());
+    }
+
+    #[tokio::test]
+    async fn remux_writes_the_selected_track() {
+        let toolkit = FakeToolkit::new().with_streams(
+            VIDEO,
+            StreamKind::Audio,
+            vec![stream(1, "aac", "eng"), stream(2, "aac", "jpn")],
+        );
+
+        let output = remux_audio(&toolkit, VIDEO, 2).await.unwrap();
+        assert!(toolkit.calls()[1].starts_with(&format!("remux_audio_track {} 2 ", VIDEO)));
+        assert!(Path::new(&output).is_file());
+        let _ = fs::remove_file(output);
+    }
+
+    #[tokio::test]
+    async fn video_without_audio_is_refused_before_transcribing() {
+        let toolkit = FakeToolkit::new().with_streams(VIDEO, StreamKind::Audio, Vec::new());
+        assert!(check_video_has_audio(&toolkit, VIDEO).await.is_some());
+
+        // An unreadable file is left for FFmpeg to report.
+        assert!(check_video_has_audio(&FakeToolkit::new(), VIDEO)
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn duration_comes_from_the_toolkit() {
+        let toolkit = FakeToolkit::new().with_duration(VIDEO, 5400.5);
+        assert_eq!(get_video_duration(&toolkit, VIDEO).await.unwrap(), 5400.5);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use crate::media_toolkit::{CliToolkit, MediaToolkit};
+use crate::probe_cache::CachedToolkit;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter};
+
+// Level files are normalized to. ReplayGain 2.0's reference: loud enough for
+// quiet lecture recordings without squashing films into the ceiling.
+const TARGET_LOUDNESS_LUFS: f64 = -18.0;
+
+// Gain is capped so the loudest peak stays below this, since the player
+// cannot limit and would otherwise clip.
+const MAX_TRUE_PEAK_DBTP: f64 = -1.0;
+
+const MIN_GAIN_DB: f64 = -20.0;
+const MAX_GAIN_DB: f64 = 12.0;
+
+// Below this the file is effectively silent and is left alone.
+const SILENCE_LUFS: f64 = -70.0;
+
+// Bumped by each `analyze_loudness` call so an older batch stops instead of
+// measuring files the library no longer shows.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// EBU R128 measurement of the first audio stream, from FFmpeg's `loudnorm`
+// filter in measurement mode.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LoudnessMeasurement {
+    // Integrated loudness, LUFS.
+    pub(crate) integrated: f64,
+    // Maximum true peak, dBTP.
+    pub(crate) true_peak: f64,
+    // Loudness range, LU.
+    pub(crate) range: f64,
+    // Gating threshold used for the integrated value, LUFS.
+    pub(crate) threshold: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LoudnessGain {
+    gain_db: f64,
+    // `gain_db` as an amplitude factor for a Web Audio GainNode.
+    linear_gain: f64,
+    measurement: LoudnessMeasurement,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LoudnessUpdate {
+    path: String,
+    gain: Option<LoudnessGain>,
+}
+
+// Stands in for loudnorm's "-inf" on silent input. JSON has no infinity, and
+// the value has to survive both the probe cache and the trip to the UI.
+const SILENT_LEVEL: f64 = -99.0;
+
+// loudnorm prints values as strings.
+fn measurement_value(json: &serde_json::Value, key: &str) -> Result<f64, FfmpegError> {
+    json[key]
+        .as_str()
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .map(|value| {
+            if value.is_finite() {
+                value
+            } else {
+                SILENT_LEVEL
+            }
+        })
+        .ok_or_else(|| {
+            FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("loudnorm output is missing {}", key),
+            )
+        })
+}
+
+// The measurement is the last `{ ... }` block loudnorm writes to stderr.
+pub(crate) fn parse_loudnorm_output(stderr: &str) -> Result<LoudnessMeasurement, FfmpegError> {
+    let block = stderr
+        .rfind('{')
+        .and_then(|start| Some((start, stderr[start..].find('}')? + start)))
+        .map(|(start, end)| &stderr[start..=end])
+        .ok_or_else(|| {
+            FfmpegError::new(
+                FfmpegErrorKind::Other,
+                "loudnorm did not report a measurement",
+            )
+        })?;
+    let json: serde_json::Value = serde_json::from_str(block).map_err(|e| {
+        FfmpegError::new(
+            FfmpegErrorKind::Other,
+            format!("Failed to parse loudnorm output: {}", e),
+        )
+    })?;
+
+    Ok(LoudnessMeasurement {
+        integrated: measurement_value(&json, "input_i")?,
+        true_peak: measurement_value(&json, "input_tp")?,
+        range: measurement_value(&json, "input_lra")?,
+        threshold: measurement_value(&json, "input_thresh")?,
+    })
+}
+
+fn gain_for(measurement: LoudnessMeasurement) -> LoudnessGain {
+    let gain_db = if measurement.integrated > SILENCE_LUFS {
+        let to_target = TARGET_LOUDNESS_LUFS - measurement.integrated;
+        let headroom = MAX_TRUE_PEAK_DBTP - measurement.true_peak;
+        to_target.min(headroom).clamp(MIN_GAIN_DB, MAX_GAIN_DB)
+    } else {
+        0.0
+    };
+
+    LoudnessGain {
+        gain_db,
+        linear_gain: 10f64.powf(gain_db / 20.0),
+        measurement,
+    }
+}
+
+pub(crate) async fn loudness_gain<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+) -> Result<LoudnessGain, String> {
+    let measurement = toolkit.measure_loudness(video_path).await?;
+    Ok(gain_for(measurement))
+}
+
+// Gain offset that brings a file to the common playback level. Measured on
+// first use (a full pass over the audio) and cached until the file changes.
+#[tauri::command]
+pub(crate) async fn get_loudness_gain(video_path: String) -> Result<LoudnessGain, String> {
+    loudness_gain(&CachedToolkit::new(CliToolkit), &video_path).await
+}
+
+// Measures a batch of files in the background, emitting `loudness-analyzed`
+// for each, so the library is ready before the files are opened. A new batch
+// replaces the one in progress.
+#[tauri::command]
+pub(crate) async fn analyze_loudness(
+    app_handle: AppHandle,
+    paths: Vec<String>,
+) -> Result<(), String> {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+    tokio::spawn(async move {
+        for path in paths {
+            if GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let gain = loudness_gain(&CachedToolkit::new(CliToolkit), &path)
+                .await
+                .inspect_err(|_e| {
+                    #[cfg(debug_assertions)]
+                    eprintln!("[Loudness] Failed to measure {}: {}", path, _e);
+                })
+                .ok();
+            let _ = app_handle.emit("loudness-analyzed", LoudnessUpdate { path, gain });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_toolkit::FakeToolkit;
+
+    #[tokio::test]
+    async fn gain_is_computed_from_the_measurement() {
+        let measurement = LoudnessMeasurement {
+            integrated: -24.0,
+            true_peak: -10.0,
+            range: 7.0,
+            threshold: -34.0,
+        };
+        let toolkit = FakeToolkit::new().with_loudness("/music/quiet.flac", measurement);
+
+        let gain = loudness_gain(&toolkit, "/music/quiet.flac").await.unwrap();
+        assert_eq!(gain.gain_db, 6.0);
+        assert!(loudness_gain(&toolkit, "/music/other.flac").await.is_err());
+    }
+
+    fn measurement(integrated: f64, true_peak: f64) -> LoudnessMeasurement {
+        LoudnessMeasurement {
+            integrated,
+            true_peak,
+            range: 5.0,
+            threshold: integrated - 10.0,
+        }
+    }
+
+    #[test]
+    fn parses_the_last_loudnorm_block() {
+        let stderr = r#"
+[Parsed_loudnorm_0 @ 0x1] Metadata {"unrelated": "block"}
+[Parsed_loudnorm_0 @ 0x1]
+{
+	"input_i" : "-23.54",
+	"input_tp" : "-4.20",
+	"input_lra" : "8.10",
+	"input_thresh" : "-34.02",
+	"output_i" : "-18.00",
+	"normalization_type" : "dynamic"
+}
+"#;
+        let parsed = parse_loudnorm_output(stderr).unwrap();
+        assert_eq!(parsed.integrated, -23.54);
+        assert_eq!(parsed.true_peak, -4.2);
+        assert_eq!(parsed.range, 8.1);
+        assert_eq!(parsed.threshold, -34.02);
+    }
+
+    #[test]
+    fn silent_input_becomes_a_finite_level() {
+        let stderr = r#"{"input_i": "-inf", "input_tp": "-inf", "input_lra": "0.00", "input_thresh": "-inf"}"#;
+        let parsed = parse_loudnorm_output(stderr).unwrap();
+        assert_eq!(parsed.integrated, SILENT_LEVEL);
+        assert!(serde_json::to_string(&parsed).is_ok());
+        assert_eq!(gain_for(parsed).gain_db, 0.0);
+    }
+
+    #[test]
+    fn missing_or_garbled_output_is_an_error() {
+        assert!(parse_loudnorm_output("Conversion failed!").is_err());
+        assert!(parse_loudnorm_output(r#"{"input_i": "-20"}"#).is_err());
+        assert!(parse_loudnorm_output("{ not json }").is_err());
+    }
+
+    #[test]
+    fn gain_is_limited_by_peak_headroom() {
+        // 8 dB to target, but only 3 dB before the peak reaches -1 dBTP.
+        assert_eq!(gain_for(measurement(-26.0, -4.0)).gain_db, 3.0);
+    }
+
+    #[test]
+    fn gain_is_clamped() {
+        assert_eq!(gain_for(measurement(-60.0, -40.0)).gain_db, MAX_GAIN_DB);
+        assert_eq!(gain_for(measurement(5.0, 0.0)).gain_db, MIN_GAIN_DB);
+        let unity = gain_for(measurement(-18.0, -5.0));
+        assert_eq!((unity.gain_db, unity.linear_gain), (0.0, 1.0));
+    }
+}
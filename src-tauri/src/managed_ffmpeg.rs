@@ -0,0 +1,307 @@
+use crate::ffmpeg::FfmpegPathInfo;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::AppHandle;
+
+// Checksum list published next to the binaries, in `sha256sum` output format.
+const CHECKSUM_FILE_NAME: &str = "SHA256SUMS";
+
+// Installed as a pair: ffprobe has to come from the same build as ffmpeg.
+const MANAGED_TOOLS: [&str; 2] = ["ffmpeg", "ffprobe"];
+
+static INSTALL_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+// Clears INSTALL_IN_PROGRESS when dropped, so an install that fails, panics
+// or is cancelled along with its task never blocks the next attempt.
+struct InstallGuard;
+
+impl InstallGuard {
+    fn acquire() -> Option<InstallGuard> {
+        (!INSTALL_IN_PROGRESS.swap(true, Ordering::SeqCst)).then_some(InstallGuard)
+    }
+}
+
+impl Drop for InstallGuard {
+    fn drop(&mut self) {
+        INSTALL_IN_PROGRESS.store(false, Ordering::SeqCst);
+    }
+}
+
+// Where the binaries are fetched from. A mirror holds one directory per
+// platform (e.g. `linux-x86_64/`) containing `ffmpeg`, `ffprobe` and
+// `SHA256SUMS`, so a local copy is just those files on disk.
+enum Mirror {
+    Http(String),
+    Local(PathBuf),
+}
+
+impl Mirror {
+    fn parse(base_url: &str) -> Result<Mirror, String> {
+        let base = base_url.trim().trim_end_matches('/');
+        let platform_dir = format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH);
+
+        if base.starts_with("http://") || base.starts_with("https://") {
+            Ok(Mirror::Http(format!("{}/{}", base, platform_dir)))
+        } else if let Some(path) = base.strip_prefix("file://") {
+            let path = urlencoding::decode(path)
+                .map_err(|e| format!("Invalid FFmpeg mirror URL {}: {}", base_url, e))?;
+            Ok(Mirror::Local(
+                PathBuf::from(path.as_ref()).join(platform_dir),
+            ))
+        } else if Path::new(base).is_absolute() {
+            Ok(Mirror::Local(PathBuf::from(base).join(platform_dir)))
+        } else {
+            Err(format!("Unsupported FFmpeg mirror location: {}", base_url))
+        }
+    }
+
+    async fn fetch_text(&self, name: &str) -> Result<String, String> {
+        match self {
+            Mirror::Http(base) => {
+                let url = format!("{}/{}", base, name);
+                let response = reqwest::get(&url)
+                    .await
+                    .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+                if !response.status().is_success() {
+                    return Err(format!(
+                        "Failed to fetch {}: HTTP {}",
+                        url,
+                        response.status()
+                    ));
+                }
+                response
+                    .text()
+                    .await
+                    .map_err(|e| format!("Failed to read {}: {}", url, e))
+            }
+            Mirror::Local(dir) => {
+                let path = dir.join(name);
+                fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+            }
+        }
+    }
+
+    async fn fetch_file(
+        &self,
+        app_handle: &AppHandle,
+        name: &str,
+        target: &PathBuf,
+        message: &str,
+    ) -> Result<(), String> {
+        match self {
+            Mirror::Http(base) => {
+                let url = format!("{}/{}", base, name);
+                crate::download_file_with_progress(app_handle, &url, target, message).await
+            }
+            Mirror::Local(dir) => {
+                let source = dir.join(name);
+                let target = target.clone();
+                let copied = tokio::task::spawn_blocking(move || fs::copy(&source, &target))
+                    .await
+                    .map_err(|e| format!("Copy task failed: {}", e))?
+                    .map_err(|e| format!("Failed to copy {} from mirror: {}", name, e))?;
+                crate::emit_download_progress(app_handle, copied, copied, message);
+                Ok(())
+            }
+        }
+    }
+}
+
+// `ffmpeg` on Unix, `ffmpeg.exe` on Windows.
+fn executable_name(tool: &str) -> String {
+    format!("{}{}", tool, std::env::consts::EXE_SUFFIX)
+}
+
+// Location of a managed binary, whether or not it has been installed yet.
+pub(crate) fn managed_binary(tool: &str) -> Option<PathBuf> {
+    crate::paths::managed_ffmpeg_dir()
+        .ok()
+        .map(|dir| dir.join(executable_name(tool)))
+}
+
+// Maps file name to lower-case hex digest. Accepts both `<hash>  <name>` and
+// the binary-mode `<hash> *<name>` forms.
+fn parse_checksums(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let hash = parts.next()?;
+            let name = parts.next()?.trim_start_matches('*');
+            Some((name.to_string(), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+fn set_executable(path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to mark {} executable: {}", path.display(), e))?;
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+    }
+
+    Ok(())
+}
+
+// Keeps the executable suffix last so Windows can still run it for validation.
+fn staging_path(install_dir: &Path, tool: &str) -> PathBuf {
+    install_dir.join(format!("{}.download{}", tool, std::env::consts::EXE_SUFFIX))
+}
+
+// Downloads, verifies and test-runs every tool under a `.download` name.
+// Nothing in the install directory is replaced until all of them pass.
+async fn stage_tools(
+    app_handle: &AppHandle,
+    mirror: &Mirror,
+    install_dir: &Path,
+) -> Result<(), String> {
+    let checksums = parse_checksums(&mirror.fetch_text(CHECKSUM_FILE_NAME).await?);
+
+    for tool in MANAGED_TOOLS {
+        let name = executable_name(tool);
+        let expected = checksums
+            .get(&name)
+            .ok_or_else(|| format!("{} has no entry for {}", CHECKSUM_FILE_NAME, name))?;
+        let staging = staging_path(install_dir, tool);
+
+        mirror
+            .fetch_file(
+                app_handle,
+                &name,
+                &staging,
+                &format!("Downloading {}", tool),
+            )
+            .await?;
+
+        let hash_path = staging.clone();
+        let actual = tokio::task::spawn_blocking(move || sha256_file(&hash_path))
+            .await
+            .map_err(|e| format!("Checksum task failed: {}", e))??;
+        if &actual != expected {
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                name, expected, actual
+            ));
+        }
+
+        set_executable(&staging)?;
+        let staged = staging.to_string_lossy().to_string();
+        match tool {
+            "ffmpeg" => crate::ffmpeg::validate_ffmpeg_path(&staged)?,
+            _ => crate::ffmpeg::validate_ffprobe_path(&staged)?,
+        }
+    }
+
+    Ok(())
+}
+
+async fn install(app_handle: &AppHandle, base_url: &str) -> Result<(), String> {
+    let mirror = Mirror::parse(base_url)?;
+    let install_dir = crate::paths::managed_ffmpeg_dir()?;
+    fs::create_dir_all(&install_dir)
+        .map_err(|e| format!("Failed to create FFmpeg directory: {}", e))?;
+
+    if let Err(err) = stage_tools(app_handle, &mirror, &install_dir).await {
+        for tool in MANAGED_TOOLS {
+            let _ = fs::remove_file(staging_path(&install_dir, tool));
+        }
+        return Err(err);
+    }
+
+    for tool in MANAGED_TOOLS {
+        let target = install_dir.join(executable_name(tool));
+        fs::rename(staging_path(&install_dir, tool), &target)
+            .map_err(|e| format!("Failed to install {}: {}", target.display(), e))?;
+    }
+
+    #[cfg(debug_assertions)]
+    println!(
+        "[FFmpeg] Installed managed FFmpeg into {}",
+        install_dir.display()
+    );
+
+    Ok(())
+}
+
+// Downloads a static FFmpeg + ffprobe into app data. Used after the custom
+// path and before anything on the system, so it is the natural fix on Linux
+// distributions whose FFmpeg is missing or too old.
+#[tauri::command]
+pub(crate) async fn install_managed_ffmpeg(
+    app_handle: AppHandle,
+    base_url: Option<String>,
+) -> Result<FfmpegPathInfo, String> {
+    let base_url = base_url
+        .filter(|u| !u.trim().is_empty())
+        .or_else(|| {
+            crate::settings::load_settings()
+                .ok()?
+                .managed_ffmpeg_base_url
+        })
+        .ok_or_else(|| {
+            "No FFmpeg download location configured. Set managed_ffmpeg_base_url in settings."
+                .to_string()
+        })?;
+
+    let Some(_install_guard) = InstallGuard::acquire() else {
+        return Err("FFmpeg is already being installed".to_string());
+    };
+    install(&app_handle, &base_url).await?;
+
+    crate::ffmpeg::invalidate_ffmpeg_cache();
+    Ok(crate::ffmpeg::resolve_ffmpeg_path_info())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_accept_text_and_binary_mode() {
+        let sums = parse_checksums("ABC123  ffmpeg\n\ndef456 *ffprobe.exe\nmalformed\n");
+        assert_eq!(sums.len(), 2);
+        assert_eq!(sums["ffmpeg"], "abc123");
+        assert_eq!(sums["ffprobe.exe"], "def456");
+    }
+
+    #[test]
+    fn second_install_is_refused_until_the_first_ends() {
+        let first = InstallGuard::acquire();
+        assert!(first.is_some());
+        assert!(InstallGuard::acquire().is_none());
+        drop(first);
+        assert!(InstallGuard::acquire().is_some());
+    }
+}
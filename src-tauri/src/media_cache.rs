@@ -0,0 +1,265 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+// Eviction runs this long after the last new file in a directory, so a
+// gallery filling in hundreds of thumbnails costs one scan, not hundreds.
+const EVICTION_DELAY: Duration = Duration::from_secs(5);
+
+// Temporary files older than this were left behind by a crash rather than
+// being written right now.
+const STALE_PARTIAL_AGE: Duration = Duration::from_secs(60 * 60);
+
+// Directories with an eviction pass pending.
+static EVICTION_SCHEDULED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+// Makes temporary names unique when two requests generate the same file.
+static PARTIAL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Subdirectory of the cache directory for one kind of generated file.
+pub(crate) fn dir(name: &str) -> Result<PathBuf, String> {
+    Ok(crate::paths::cache_dir()?.join(name))
+}
+
+// Hex name identifying the current contents of a media file (canonical path,
+// size and modification time) plus `variant`, such as a thumbnail's time and
+// width. An edited or replaced file gets fresh names while unchanged files
+// reuse theirs.
+pub(crate) fn file_digest(path: &str, variant: &str) -> Result<String, String> {
+    let canonical =
+        fs::canonicalize(path).map_err(|e| format!("Failed to resolve {}: {}", path, e))?;
+    let metadata =
+        fs::metadata(&canonical).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_nanos())
+        .unwrap_or(0);
+
+    let mut hasher = Sha256::new();
+    hasher.update(canonical.to_string_lossy().as_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(modified.to_le_bytes());
+    hasher.update(variant.as_bytes());
+    Ok(hasher
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+fn is_partial(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().contains(".part."))
+}
+
+// Eviction goes by modification time, since access times are often disabled
+// or coarse, so a cache hit bumps it.
+fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+// Deletes the least recently used files until `dir` fits in `max_bytes`.
+fn evict(dir: &Path, max_bytes: u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+
+    let mut files: Vec<(PathBuf, u64, SystemTime)> = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        if is_partial(&path) {
+            if now.duration_since(modified).unwrap_or_default() > STALE_PARTIAL_AGE {
+                let _ = fs::remove_file(&path);
+            }
+            continue;
+        }
+        files.push((path, metadata.len(), modified));
+    }
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= max_bytes {
+        return;
+    }
+
+    files.sort_by_key(|(_, _, modified)| *modified);
+    let mut _removed = 0;
+    for (path, size, _) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= size;
+            _removed += 1;
+        }
+    }
+    #[cfg(debug_assertions)]
+    println!(
+        "[Media Cache] Evicted {} files from {}",
+        _removed,
+        dir.display()
+    );
+}
+
+fn schedule_eviction(dir: PathBuf, max_bytes: u64) {
+    {
+        let mut scheduled = EVICTION_SCHEDULED.lock().unwrap_or_else(|e| e.into_inner());
+        if scheduled.contains(&dir) {
+            return;
+        }
+        scheduled.push(dir.clone());
+    }
+    tokio::spawn(async move {
+        tokio::time::sleep(EVICTION_DELAY).await;
+        EVICTION_SCHEDULED
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|scheduled| *scheduled != dir);
+        let _ = tokio::task::spawn_blocking(move || evict(&dir, max_bytes)).await;
+    });
+}
+
+// Makes sure `target` exists, generating it with `write` if it is not cached
+// yet. `write` gets a temporary path in the same directory with the same
+// extension, and the result is only renamed into place once complete, so a
+// failed or interrupted run never leaves a truncated file behind for later
+// lookups. The directory is then trimmed back to `max_bytes`.
+pub(crate) async fn get_or_create<F, Fut, E>(
+    target: &Path,
+    max_bytes: u64,
+    write: F,
+) -> Result<(), String>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+    E: Into<String>,
+{
+    if target.is_file() {
+        touch(target);
+        return Ok(());
+    }
+
+    let dir = target
+        .parent()
+        .ok_or_else(|| format!("Invalid cache path {}", target.display()))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    let extension = target
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let partial = target.with_extension(format!(
+        "{}.part.{}",
+        PARTIAL_COUNTER.fetch_add(1, Ordering::Relaxed),
+        extension
+    ));
+
+    if let Err(e) = write(partial.to_string_lossy().to_string()).await {
+        let _ = fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    // FFmpeg exits successfully without writing anything when there was no
+    // frame to take, e.g. a seek past the end.
+    if !partial.is_file() {
+        return Err(format!(
+            "FFmpeg produced no output for {}",
+            target.display()
+        ));
+    }
+    fs::rename(&partial, target).map_err(|e| {
+        let _ = fs::remove_file(&partial);
+        format!("Failed to save {}: {}", target.display(), e)
+    })?;
+
+    schedule_eviction(dir.to_path_buf(), max_bytes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("media-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // A `size`-byte file last modified `age_secs` ago.
+    fn cached_file(dir: &Path, name: &str, size: usize, age_secs: u64) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, vec![0u8; size]).unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn digest_changes_with_variant_and_modification_time() {
+        let dir = scratch("digest");
+        let file = cached_file(&dir, "film.mkv", 10, 100);
+        let path = file.to_string_lossy().to_string();
+
+        let first = file_digest(&path, "320@1000").unwrap();
+        assert_eq!(first.len(), 32);
+        assert_eq!(file_digest(&path, "320@1000").unwrap(), first);
+        assert_ne!(file_digest(&path, "640@1000").unwrap(), first);
+
+        touch(&file);
+        assert_ne!(file_digest(&path, "320@1000").unwrap(), first);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn eviction_removes_oldest_files_first() {
+        let dir = scratch("evict");
+        let oldest = cached_file(&dir, "a.jpg", 100, 300);
+        let older = cached_file(&dir, "b.jpg", 100, 200);
+        let newest = cached_file(&dir, "c.jpg", 100, 100);
+
+        evict(&dir, 150);
+
+        assert!(!oldest.exists());
+        assert!(!older.exists());
+        assert!(newest.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn eviction_leaves_files_being_written() {
+        let dir = scratch("evict-partial");
+        let partial = cached_file(&dir, "a.0.part.jpg", 1000, 300);
+        let kept = cached_file(&dir, "b.jpg", 100, 100);
+        let stale = cached_file(&dir, "c.1.part.jpg", 10, 2 * 60 * 60);
+
+        // The partial file alone is over the limit but is neither counted
+        // nor removed; one left over from a crash is cleaned up.
+        evict(&dir, 150);
+
+        assert!(partial.exists());
+        assert!(kept.exists());
+        assert!(!stale.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
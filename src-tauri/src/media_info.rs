@@ -0,0 +1,417 @@
+use crate::ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use crate::media_toolkit::{CliToolkit, MediaToolkit};
+use crate::probe_cache::CachedToolkit;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+// Everything ffprobe reports about a file, for the media info panel. Fields
+// are optional because containers and codecs report very different subsets.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MediaInfo {
+    pub(crate) format_name: Option<String>,
+    pub(crate) format_long_name: Option<String>,
+    pub(crate) duration: Option<f64>,
+    pub(crate) size_bytes: Option<u64>,
+    pub(crate) bit_rate: Option<u64>,
+    pub(crate) tags: BTreeMap<String, String>,
+    pub(crate) video_streams: Vec<VideoStreamInfo>,
+    pub(crate) audio_streams: Vec<AudioStreamInfo>,
+    pub(crate) subtitle_streams: Vec<SubtitleStreamInfo>,
+    pub(crate) attachments: Vec<AttachmentInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct VideoStreamInfo {
+    pub(crate) index: i64,
+    pub(crate) codec_name: Option<String>,
+    pub(crate) codec_long_name: Option<String>,
+    pub(crate) profile: Option<String>,
+    pub(crate) width: Option<u32>,
+    pub(crate) height: Option<u32>,
+    pub(crate) sample_aspect_ratio: Option<String>,
+    pub(crate) display_aspect_ratio: Option<String>,
+    pub(crate) frame_rate: Option<f64>,
+    pub(crate) pixel_format: Option<String>,
+    pub(crate) bit_depth: Option<u32>,
+    pub(crate) color_range: Option<String>,
+    pub(crate) color_space: Option<String>,
+    pub(crate) color_primaries: Option<String>,
+    pub(crate) color_transfer: Option<String>,
+    // "HDR10", "HDR10+", "HLG" or "Dolby Vision"; None for SDR.
+    pub(crate) hdr_format: Option<String>,
+    // Clockwise degrees the player has to rotate the picture, 0 if upright.
+    pub(crate) rotation: i32,
+    pub(crate) bit_rate: Option<u64>,
+    pub(crate) language: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) dispositions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AudioStreamInfo {
+    pub(crate) index: i64,
+    pub(crate) codec_name: Option<String>,
+    pub(crate) codec_long_name: Option<String>,
+    pub(crate) profile: Option<String>,
+    pub(crate) sample_rate: Option<u32>,
+    pub(crate) channels: Option<u32>,
+    pub(crate) channel_layout: Option<String>,
+    pub(crate) sample_format: Option<String>,
+    pub(crate) bit_rate: Option<u64>,
+    pub(crate) language: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) dispositions: Vec<String>,
+    // Ogg and Opus files keep their Vorbis comments here rather than on the
+    // container.
+    pub(crate) tags: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubtitleStreamInfo {
+    pub(crate) index: i64,
+    pub(crate) codec_name: Option<String>,
+    pub(crate) codec_long_name: Option<String>,
+    pub(crate) language: Option<String>,
+    pub(crate) title: Option<String>,
+    // Flags ffprobe reports as set, e.g. "default", "forced",
+    // "hearing_impaired".
+    pub(crate) dispositions: Vec<String>,
+}
+
+// Fonts and cover images embedded in the container (mostly MKV).
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AttachmentInfo {
+    pub(crate) index: i64,
+    pub(crate) file_name: Option<String>,
+    pub(crate) mime_type: Option<String>,
+    pub(crate) size_bytes: Option<u64>,
+    // Cover art stored as a one-frame picture stream (MP3, M4A, FLAC) rather
+    // than as an MKV attachment. Only these can be extracted as images.
+    pub(crate) is_attached_pic: bool,
+}
+
+// ffprobe prints most numbers as strings ("48000", "5.120000") and a few as
+// JSON numbers; accept either.
+pub(crate) fn number<T: std::str::FromStr>(value: &Value) -> Option<T> {
+    match value {
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Number(n) => n.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+// "unknown" and "N/A" are ffprobe's way of saying the field is not set.
+pub(crate) fn text(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .filter(|s| !s.is_empty() && *s != "unknown" && *s != "N/A")
+        .map(str::to_string)
+}
+
+// Parses a rational like "24000/1001". "0/0" means unknown.
+fn frame_rate(value: &Value) -> Option<f64> {
+    let (num, den) = value.as_str()?.split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+fn tags(value: &Value) -> BTreeMap<String, String> {
+    value
+        .as_object()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn dispositions(stream: &Value) -> Vec<String> {
+    stream["disposition"]
+        .as_object()
+        .map(|flags| {
+            flags
+                .iter()
+                .filter(|(_, set)| set.as_i64() == Some(1))
+                .map(|(name, _)| name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn side_data<'a>(stream: &'a Value, side_data_type: &str) -> Option<&'a Value> {
+    stream["side_data_list"]
+        .as_array()?
+        .iter()
+        .find(|entry| entry["side_data_type"].as_str() == Some(side_data_type))
+}
+
+fn hdr_format(stream: &Value) -> Option<String> {
+    if side_data(stream, "DOVI configuration record").is_some() {
+        return Some("Dolby Vision".to_string());
+    }
+    match stream["color_transfer"].as_str()? {
+        "smpte2084" => {
+            if side_data(stream, "HDR Dynamic Metadata SMPTE2094-40 (HDR10+)").is_some() {
+                Some("HDR10+".to_string())
+            } else {
+                Some("HDR10".to_string())
+            }
+        }
+        "arib-std-b67" => Some("HLG".to_string()),
+        _ => None,
+    }
+}
+
+// Newer ffprobe reports rotation in the display matrix side data
+// (counter-clockwise, so negated here); older builds use a `rotate` tag.
+fn rotation(stream: &Value) -> i32 {
+    let degrees = side_data(stream, "Display Matrix")
+        .and_then(|matrix| number::<f64>(&matrix["rotation"]))
+        .map(|ccw| -ccw)
+        .or_else(|| number::<f64>(&stream["tags"]["rotate"]))
+        .unwrap_or(0.0);
+    (degrees.round() as i32).rem_euclid(360)
+}
+
+fn video_stream(stream: &Value, index: i64) -> VideoStreamInfo {
+    VideoStreamInfo {
+        index,
+        codec_name: text(&stream["codec_name"]),
+        codec_long_name: text(&stream["codec_long_name"]),
+        profile: text(&stream["profile"]),
+        width: number(&stream["width"]),
+        height: number(&stream["height"]),
+        sample_aspect_ratio: text(&stream["sample_aspect_ratio"]),
+        display_aspect_ratio: text(&stream["display_aspect_ratio"]),
+        frame_rate: frame_rate(&stream["avg_frame_rate"])
+            .or_else(|| frame_rate(&stream["r_frame_rate"])),
+        pixel_format: text(&stream["pix_fmt"]),
+        bit_depth: number(&stream["bits_per_raw_sample"]),
+        color_range: text(&stream["color_range"]),
+        color_space: text(&stream["color_space"]),
+        color_primaries: text(&stream["color_primaries"]),
+        color_transfer: text(&stream["color_transfer"]),
+        hdr_format: hdr_format(stream),
+        rotation: rotation(stream),
+        bit_rate: number(&stream["bit_rate"]),
+        language: text(&stream["tags"]["language"]),
+        title: text(&stream["tags"]["title"]),
+        dispositions: dispositions(stream),
+    }
+}
+
+fn audio_stream(stream: &Value, index: i64) -> AudioStreamInfo {
+    AudioStreamInfo {
+        index,
+        codec_name: text(&stream["codec_name"]),
+        codec_long_name: text(&stream["codec_long_name"]),
+        profile: text(&stream["profile"]),
+        sample_rate: number(&stream["sample_rate"]),
+        channels: number(&stream["channels"]),
+        channel_layout: text(&stream["channel_layout"]),
+        sample_format: text(&stream["sample_fmt"]),
+        bit_rate: number(&stream["bit_rate"]),
+        language: text(&stream["tags"]["language"]),
+        title: text(&stream["tags"]["title"]),
+        dispositions: dispositions(stream),
+        tags: tags(&stream["tags"]),
+    }
+}
+
+fn subtitle_stream(stream: &Value, index: i64) -> SubtitleStreamInfo {
+    SubtitleStreamInfo {
+        index,
+        codec_name: text(&stream["codec_name"]),
+        codec_long_name: text(&stream["codec_long_name"]),
+        language: text(&stream["tags"]["language"]),
+        title: text(&stream["tags"]["title"]),
+        dispositions: dispositions(stream),
+    }
+}
+
+fn cover_art_mime_type(codec_name: &Value) -> Option<String> {
+    let mime_type = match codec_name.as_str()? {
+        "mjpeg" => "image/jpeg",
+        "png" => "image/png",
+        "bmp" => "image/bmp",
+        "webp" => "image/webp",
+        _ => return None,
+    };
+    Some(mime_type.to_string())
+}
+
+fn attachment(stream: &Value, index: i64) -> AttachmentInfo {
+    AttachmentInfo {
+        index,
+        file_name: text(&stream["tags"]["filename"]),
+        mime_type: text(&stream["tags"]["mimetype"]),
+        size_bytes: number(&stream["extradata_size"]),
+        is_attached_pic: false,
+    }
+}
+
+// Parses `ffprobe -show_format -show_streams -of json` output.
+pub(crate) fn parse_media_info(stdout: &str) -> Result<MediaInfo, FfmpegError> {
+    let parsed: Value = serde_json::from_str(stdout).map_err(|e| {
+        FfmpegError::new(
+            FfmpegErrorKind::Other,
+            format!("Failed to parse ffprobe JSON: {} (stdout: {})", e, stdout),
+        )
+    })?;
+
+    let format = &parsed["format"];
+    let mut info = MediaInfo {
+        format_name: text(&format["format_name"]),
+        format_long_name: text(&format["format_long_name"]),
+        duration: number(&format["duration"]),
+        size_bytes: number(&format["size"]),
+        bit_rate: number(&format["bit_rate"]),
+        tags: tags(&format["tags"]),
+        ..MediaInfo::default()
+    };
+
+    for stream in parsed["streams"].as_array().into_iter().flatten() {
+        let Some(index) = stream["index"].as_i64() else {
+            continue;
+        };
+        match stream["codec_type"].as_str() {
+            // Cover art is stored as a one-frame video stream.
+            Some("video") if stream["disposition"]["attached_pic"].as_i64() == Some(1) => {
+                info.attachments.push(AttachmentInfo {
+                    index,
+                    file_name: text(&stream["tags"]["filename"]),
+                    mime_type: cover_art_mime_type(&stream["codec_name"]),
+                    size_bytes: None,
+                    is_attached_pic: true,
+                });
+            }
+            Some("video") => info.video_streams.push(video_stream(stream, index)),
+            Some("audio") => info.audio_streams.push(audio_stream(stream, index)),
+            Some("subtitle") => info.subtitle_streams.push(subtitle_stream(stream, index)),
+            Some("attachment") => info.attachments.push(attachment(stream, index)),
+            _ => {}
+        }
+    }
+
+    Ok(info)
+}
+
+// Full container and per-stream details for the media info panel.
+#[tauri::command]
+pub(crate) async fn get_media_info(video_path: String) -> Result<MediaInfo, FfmpegError> {
+    CachedToolkit::new(CliToolkit)
+        .probe_media(&video_path)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_format_and_streams_by_type() {
+        let stdout = r#"{
+            "streams": [
+                {"index": 0, "codec_type": "video", "codec_name": "hevc", "width": 3840,
+                 "height": 2160, "avg_frame_rate": "24000/1001", "pix_fmt": "yuv420p10le",
+                 "color_transfer": "smpte2084", "disposition": {"default": 1, "forced": 0}},
+                {"index": 1, "codec_type": "audio", "codec_name": "eac3", "sample_rate": "48000",
+                 "channels": 6, "channel_layout": "5.1(side)", "tags": {"language": "eng"}},
+                {"index": 2, "codec_type": "subtitle", "codec_name": "subrip",
+                 "tags": {"language": "ger", "title": "Forced"}, "disposition": {"forced": 1}},
+                {"index": 3, "codec_type": "attachment", "extradata_size": 1024,
+                 "tags": {"filename": "font.ttf", "mimetype": "font/ttf"}},
+                {"codec_type": "audio"}
+            ],
+            "format": {"format_name": "matroska,webm", "duration": "5400.250000",
+                       "size": "4000000000", "bit_rate": "N/A", "tags": {"title": "Film"}}
+        }"#;
+        let info = parse_media_info(stdout).unwrap();
+
+        assert_eq!(info.format_name.as_deref(), Some("matroska,webm"));
+        assert_eq!(info.duration, Some(5400.25));
+        assert_eq!(info.size_bytes, Some(4_000_000_000));
+        assert_eq!(info.bit_rate, None);
+        assert_eq!(info.tags["title"], "Film");
+
+        let video = &info.video_streams[0];
+        assert_eq!((video.width, video.height), (Some(3840), Some(2160)));
+        assert!((video.frame_rate.unwrap() - 23.976).abs() < 0.001);
+        assert_eq!(video.hdr_format.as_deref(), Some("HDR10"));
+        assert_eq!(video.dispositions, ["default"]);
+
+        assert_eq!(info.audio_streams.len(), 1);
+        assert_eq!(info.audio_streams[0].channels, Some(6));
+        assert_eq!(info.audio_streams[0].sample_rate, Some(48000));
+        assert_eq!(info.subtitle_streams[0].dispositions, ["forced"]);
+        assert_eq!(info.attachments[0].file_name.as_deref(), Some("font.ttf"));
+        assert_eq!(info.attachments[0].size_bytes, Some(1024));
+    }
+
+    #[test]
+    fn cover_art_is_an_attachment_not_a_video_stream() {
+        let stdout = r#"{"streams": [
+            {"index": 0, "codec_type": "audio", "codec_name": "mp3"},
+            {"index": 1, "codec_type": "video", "codec_name": "mjpeg",
+             "disposition": {"attached_pic": 1}}
+        ], "format": {}}"#;
+        let info = parse_media_info(stdout).unwrap();
+
+        assert!(info.video_streams.is_empty());
+        assert_eq!(info.attachments.len(), 1);
+        assert!(info.attachments[0].is_attached_pic);
+        assert_eq!(info.attachments[0].mime_type.as_deref(), Some("image/jpeg"));
+    }
+
+    #[test]
+    fn rotation_comes_from_display_matrix_or_tag() {
+        let matrix = serde_json::json!({
+            "side_data_list": [{"side_data_type": "Display Matrix", "rotation": -90}]
+        });
+        assert_eq!(rotation(&matrix), 90);
+        assert_eq!(
+            rotation(&serde_json::json!({"tags": {"rotate": "270"}})),
+            270
+        );
+        assert_eq!(rotation(&serde_json::json!({})), 0);
+    }
+
+    #[test]
+    fn dolby_vision_wins_over_transfer_function() {
+        let stream = serde_json::json!({
+            "color_transfer": "smpte2084",
+            "side_data_list": [{"side_data_type": "DOVI configuration record"}]
+        });
+        assert_eq!(hdr_format(&stream).as_deref(), Some("Dolby Vision"));
+        let hlg = serde_json::json!({"color_transfer": "arib-std-b67"});
+        assert_eq!(hdr_format(&hlg).as_deref(), Some("HLG"));
+        assert_eq!(
+            hdr_format(&serde_json::json!({"color_transfer": "bt709"})),
+            None
+        );
+    }
+
+    #[test]
+    fn unknown_values_and_bad_rates_are_none() {
+        assert_eq!(text(&Value::from("unknown")), None);
+        assert_eq!(text(&Value::from("")), None);
+        assert_eq!(frame_rate(&Value::from("0/0")), None);
+        assert_eq!(number::<u32>(&Value::from(" 44100 ")), Some(44100));
+    }
+
+    #[test]
+    fn invalid_json_is_an_error() {
+        let Err(err) = parse_media_info("not json") else {
+            panic!("parsed invalid JSON");
+        };
+        assert_eq!(err.kind, FfmpegErrorKind::Other);
+    }
+}
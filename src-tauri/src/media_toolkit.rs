@@ -0,0 +1,978 @@
+use crate::capabilities::{self, Component, FfmpegCapabilities};
+use crate::chapters::{self, Chapter};
+use crate::ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use crate::integrity::{self, DecodeCheck, DecodeError};
+use crate::loudness::{self, LoudnessMeasurement};
+use crate::media_info::{self, MediaInfo};
+use crate::process_scheduler::{self, Priority};
+use crate::seek_preview::SpriteLayout;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+// Every ffmpeg/ffprobe invocation goes through this trait so the logic around
+// it (track filtering, temp files, subtitle generation) can run against a fake
+// in tests instead of a real FFmpeg install. `CliToolkit` is the only
+// production implementation.
+pub(crate) trait MediaToolkit: Sync {
+    // Whether ffprobe can be run at all.
+    async fn is_available(&self) -> bool;
+
+    async fn probe_streams(
+        &self,
+        path: &str,
+        kind: StreamKind,
+    ) -> Result<Vec<ProbedStream>, FfmpegError>;
+
+    // Container and per-stream details for the media info panel.
+    async fn probe_media(&self, path: &str) -> Result<MediaInfo, FfmpegError>;
+
+    async fn probe_chapters(&self, path: &str) -> Result<Vec<Chapter>, FfmpegError>;
+
+    // Only used for library durations, so it yields to everything else in the
+    // process scheduler.
+    async fn probe_duration(&self, path: &str) -> Result<f64, FfmpegError>;
+
+    // Returns the selected subtitle stream converted to SRT.
+    async fn extract_subtitle(&self, path: &str, stream_index: i64) -> Result<String, FfmpegError>;
+
+    // Stream-copies all video plus the one selected audio stream into `output`.
+    async fn remux_audio_track(
+        &self,
+        path: &str,
+        audio_stream_index: i64,
+        output: &str,
+    ) -> Result<(), FfmpegError>;
+
+    // Writes 16 kHz mono 16-bit PCM WAV, the input Whisper expects.
+    async fn extract_audio(&self, path: &str, output: &str) -> Result<(), FfmpegError>;
+
+    // Converts to "mp4", "webm" or "mkv" (remux only).
+    async fn transcode(
+        &self,
+        input: &str,
+        output: &str,
+        target_format: &str,
+    ) -> Result<(), FfmpegError>;
+
+    // Stream-copies the video and every audio stream into `container` ("mp4",
+    // "m4a", "webm" or "mkv"), for codecs the webview plays in the wrong
+    // container.
+    async fn remux(&self, input: &str, output: &str, container: &str) -> Result<(), FfmpegError>;
+
+    // Copies the video and re-encodes every audio stream to the container's
+    // usual codec: Opus/Vorbis for "webm", AAC otherwise.
+    async fn transcode_audio(
+        &self,
+        input: &str,
+        output: &str,
+        container: &str,
+    ) -> Result<(), FfmpegError>;
+
+    // Writes an attached picture stream to `output`: copied as-is for .jpg,
+    // re-encoded to PNG otherwise.
+    async fn extract_cover_art(
+        &self,
+        path: &str,
+        stream_index: i64,
+        output: &str,
+    ) -> Result<(), FfmpegError>;
+
+    // Writes one frame from `time` seconds in, scaled down to at most `width`
+    // pixels wide: WebP for a .webp output, JPEG otherwise.
+    async fn extract_frame(
+        &self,
+        path: &str,
+        time: f64,
+        width: u32,
+        output: &str,
+    ) -> Result<(), FfmpegError>;
+
+    // Writes a JPEG grid of frames sampled every `layout.interval` seconds.
+    async fn render_sprite_sheet(
+        &self,
+        path: &str,
+        layout: SpriteLayout,
+        output: &str,
+    ) -> Result<(), FfmpegError>;
+
+    // Writes a silent looping clip of the `sample_secs`-long excerpts starting
+    // at `starts`, joined in order: animated WebP for a .webp output, WebM
+    // otherwise.
+    async fn render_hover_preview(
+        &self,
+        path: &str,
+        starts: &[f64],
+        sample_secs: f64,
+        width: u32,
+        output: &str,
+    ) -> Result<(), FfmpegError>;
+
+    // Decodes every stream to the null muxer, collecting decode errors and
+    // calling `on_progress` with the decode position in seconds.
+    async fn decode_check(
+        &self,
+        path: &str,
+        on_progress: impl Fn(f64) + Send + Sync,
+    ) -> Result<DecodeCheck, FfmpegError>;
+
+    // EBU R128 loudness of the first audio stream.
+    async fn measure_loudness(&self, path: &str) -> Result<LoudnessMeasurement, FfmpegError>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+}
+
+impl StreamKind {
+    fn selector(self) -> &'static str {
+        match self {
+            StreamKind::Video => "v",
+            StreamKind::Audio => "a",
+            StreamKind::Subtitle => "s",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct ProbedStream {
+    pub(crate) index: i64,
+    pub(crate) codec_name: Option<String>,
+    pub(crate) language: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) channels: Option<i64>,
+    pub(crate) is_default: bool,
+    // Cover art stored as a one-frame video stream.
+    #[serde(default)]
+    pub(crate) is_attached_pic: bool,
+}
+
+// Talks to the resolved ffmpeg/ffprobe binaries.
+pub(crate) struct CliToolkit;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn parse_probed_streams(stdout: &str) -> Result<Vec<ProbedStream>, FfmpegError> {
+    let parsed: serde_json::Value = serde_json::from_str(stdout).map_err(|e| {
+        FfmpegError::new(
+            FfmpegErrorKind::Other,
+            format!("Failed to parse ffprobe JSON: {} (stdout: {})", e, stdout),
+        )
+    })?;
+
+    let streams = parsed["streams"].as_array().ok_or_else(|| {
+        FfmpegError::new(
+            FfmpegErrorKind::Other,
+            "ffprobe JSON missing 'streams' array",
+        )
+    })?;
+
+    Ok(streams
+        .iter()
+        .filter_map(|stream| {
+            Some(ProbedStream {
+                index: stream["index"].as_i64()?,
+                codec_name: stream["codec_name"].as_str().map(str::to_string),
+                language: stream["tags"]["language"].as_str().map(str::to_string),
+                title: stream["tags"]["title"].as_str().map(str::to_string),
+                channels: stream["channels"].as_i64(),
+                is_default: stream["disposition"]["default"].as_i64().unwrap_or(0) == 1,
+                is_attached_pic: stream["disposition"]["attached_pic"].as_i64().unwrap_or(0) == 1,
+            })
+        })
+        .collect())
+}
+
+// Encoder name plus the quality arguments it needs.
+pub(crate) type EncoderChoice = (&'static str, &'static [&'static str]);
+
+// Encoder preferences per conversion target, best first. Distro and custom
+// FFmpeg builds often lack the GPL/external libraries, so each list ends with
+// something more commonly available.
+const MP4_VIDEO_ENCODERS: &[EncoderChoice] = &[
+    ("libx264", &["-preset", "medium", "-crf", "23"]),
+    ("libopenh264", &["-b:v", "6M"]),
+    ("h264_videotoolbox", &["-b:v", "6M"]),
+    ("h264_mf", &["-b:v", "6M"]),
+];
+const MP4_AUDIO_ENCODERS: &[EncoderChoice] = &[
+    ("aac", &["-b:a", "192k"]),
+    ("libfdk_aac", &["-b:a", "192k"]),
+];
+pub(crate) const WEBM_VIDEO_ENCODERS: &[EncoderChoice] = &[
+    ("libvpx-vp9", &["-crf", "30", "-b:v", "0"]),
+    ("libvpx", &["-crf", "10", "-b:v", "2M"]),
+    ("libsvtav1", &["-crf", "35"]),
+];
+const WEBM_AUDIO_ENCODERS: &[EncoderChoice] = &[("libopus", &[]), ("libvorbis", &["-q:a", "5"])];
+
+// FFmpeg muxer for each container `remux` and `transcode_audio` can write.
+fn muxer_for(container: &str) -> Result<&'static str, FfmpegError> {
+    match container {
+        "mp4" => Ok("mp4"),
+        "m4a" => Ok("ipod"),
+        "webm" => Ok("webm"),
+        "mkv" => Ok("matroska"),
+        _ => Err(FfmpegError::new(
+            FfmpegErrorKind::Other,
+            format!("Unsupported format: {}", container),
+        )),
+    }
+}
+
+// Picks the first encoder the resolved FFmpeg provides. Without capability
+// data we keep the historical first choice and let FFmpeg report failures.
+fn pick_encoder(
+    caps: Option<&FfmpegCapabilities>,
+    choices: &[EncoderChoice],
+    kind: &str,
+    target_format: &str,
+) -> Result<EncoderChoice, FfmpegError> {
+    let Some(caps) = caps else {
+        return Ok(choices[0]);
+    };
+    choices
+        .iter()
+        .find(|(name, _)| caps.has(Component::Encoder, name))
+        .copied()
+        .ok_or_else(|| {
+            let tried: Vec<&str> = choices.iter().map(|(name, _)| *name).collect();
+            FfmpegError::new(
+                FfmpegErrorKind::MissingCodec,
+                format!(
+                    "Your FFmpeg build has no {} encoder for {} (tried {}). Install a full FFmpeg build or convert to MKV instead.",
+                    kind,
+                    target_format.to_uppercase(),
+                    tried.join(", ")
+                ),
+            )
+        })
+}
+
+// Resolves the muxer and (video, audio) encoders for a conversion target so an
+// unsupported build fails with a clear message instead of raw stderr halfway
+// through. No encoders means stream copy.
+fn plan_conversion(
+    caps: Option<&FfmpegCapabilities>,
+    target_format: &str,
+) -> Result<Option<(EncoderChoice, EncoderChoice)>, FfmpegError> {
+    let (muxer, codecs) = match target_format {
+        "mp4" => (
+            "mp4",
+            Some((
+                pick_encoder(caps, MP4_VIDEO_ENCODERS, "H.264", target_format)?,
+                pick_encoder(caps, MP4_AUDIO_ENCODERS, "AAC", target_format)?,
+            )),
+        ),
+        "webm" => (
+            "webm",
+            Some((
+                pick_encoder(caps, WEBM_VIDEO_ENCODERS, "VP9/VP8/AV1", target_format)?,
+                pick_encoder(caps, WEBM_AUDIO_ENCODERS, "Opus/Vorbis", target_format)?,
+            )),
+        ),
+        "mkv" => ("matroska", None), // Just remux, no re-encoding
+        _ => {
+            return Err(FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("Unsupported format: {}", target_format),
+            ))
+        }
+    };
+
+    if let Some(caps) = caps {
+        if !caps.has(Component::Muxer, muxer) {
+            return Err(FfmpegError::new(
+                FfmpegErrorKind::MissingCodec,
+                format!(
+                    "Your FFmpeg build cannot write {} files (missing {} muxer).",
+                    target_format.to_uppercase(),
+                    muxer
+                ),
+            ));
+        }
+    }
+
+    Ok(codecs)
+}
+
+impl MediaToolkit for CliToolkit {
+    async fn is_available(&self) -> bool {
+        tokio::task::spawn_blocking(|| {
+            // Resolving the path can run probes of its own, so it happens
+            // before taking a slot.
+            let mut cmd = crate::get_ffprobe_command();
+            let Ok(_permit) = process_scheduler::acquire_blocking(Priority::Interactive) else {
+                return false;
+            };
+            cmd.arg("-version")
+                .output()
+                .map(|o| o.status.success())
+                .unwrap_or(false)
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    async fn probe_streams(
+        &self,
+        path: &str,
+        kind: StreamKind,
+    ) -> Result<Vec<ProbedStream>, FfmpegError> {
+        let mut cmd = crate::get_ffprobe_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-select_streams",
+            kind.selector(),
+            "-show_entries",
+            "stream=index,codec_name,channels:stream_tags=language,title:stream_disposition=default,attached_pic",
+            "-of",
+            "json",
+            path,
+        ]);
+
+        let output =
+            crate::run_with_timeout(cmd, PROBE_TIMEOUT, "ffprobe", Priority::Interactive).await?;
+
+        if !output.status.success() {
+            return Err(FfmpegError::from_stderr("ffprobe failed", &output.stderr));
+        }
+
+        parse_probed_streams(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    async fn probe_media(&self, path: &str) -> Result<MediaInfo, FfmpegError> {
+        let mut cmd = crate::get_ffprobe_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-show_format",
+            "-show_streams",
+            "-of",
+            "json",
+            path,
+        ]);
+
+        let output =
+            crate::run_with_timeout(cmd, PROBE_TIMEOUT, "ffprobe", Priority::Interactive).await?;
+
+        if !output.status.success() {
+            return Err(FfmpegError::from_stderr("ffprobe failed", &output.stderr));
+        }
+
+        media_info::parse_media_info(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    async fn probe_chapters(&self, path: &str) -> Result<Vec<Chapter>, FfmpegError> {
+        let mut cmd = crate::get_ffprobe_command();
+        cmd.args(["-v", "error", "-show_chapters", "-of", "json", path]);
+
+        let output =
+            crate::run_with_timeout(cmd, PROBE_TIMEOUT, "ffprobe", Priority::Interactive).await?;
+
+        if !output.status.success() {
+            return Err(FfmpegError::from_stderr("ffprobe failed", &output.stderr));
+        }
+
+        chapters::parse_chapters(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    async fn probe_duration(&self, path: &str) -> Result<f64, FfmpegError> {
+        let mut cmd = crate::get_ffprobe_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            path,
+        ]);
+        let output =
+            crate::run_with_timeout(cmd, PROBE_TIMEOUT, "ffprobe", Priority::Background).await?;
+        if !output.status.success() {
+            return Err(FfmpegError::from_stderr("ffprobe failed", &output.stderr));
+        }
+
+        let duration_str = String::from_utf8_lossy(&output.stdout);
+        duration_str.trim().parse::<f64>().map_err(|e| {
+            FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("Invalid duration '{}': {}", duration_str.trim(), e),
+            )
+        })
+    }
+
+    // FFmpeg handles codec conversion (e.g. ASS → SRT) automatically when the
+    // output format is forced to `srt`. Sending output to `pipe:1` means no
+    // temp file is written to disk.
+    async fn extract_subtitle(&self, path: &str, stream_index: i64) -> Result<String, FfmpegError> {
+        capabilities::ensure_supported(
+            &[(Component::Encoder, "srt"), (Component::Muxer, "srt")],
+            "extract embedded subtitles",
+        )
+        .await?;
+
+        // Subtitle packets are interleaved throughout the container, so extraction
+        // reads the whole file — scale the timeout with size so large MKVs don't hit
+        // a premature cap. Floor 30 s, cap 30 min.
+        let timeout = crate::size_scaled_timeout(path, 30, 1800);
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-i",
+            path,
+            "-map",
+            &format!("0:{}", stream_index),
+            "-f",
+            "srt",
+            "pipe:1",
+        ]);
+
+        let output = crate::run_with_timeout(cmd, timeout, "ffmpeg", Priority::Interactive).await?;
+
+        if !output.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg failed to extract subtitle",
+                &output.stderr,
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    async fn remux_audio_track(
+        &self,
+        path: &str,
+        audio_stream_index: i64,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        capabilities::ensure_supported(&[(Component::Muxer, "matroska")], "switch audio tracks")
+            .await?;
+
+        // Stream-copy reads the input and writes a near-equal-size output, so the old
+        // fixed 120 s cap starved multi-GB files. Scale with size. Floor 120 s, cap 1 h.
+        let timeout = crate::size_scaled_timeout(path, 120, 3600);
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-i",
+            path,
+            "-map",
+            "0:V?",
+            "-map",
+            &format!("0:{}", audio_stream_index),
+            "-c",
+            "copy",
+            "-y",
+            output,
+        ]);
+
+        let result = crate::run_with_timeout(cmd, timeout, "ffmpeg", Priority::Interactive).await?;
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg remux failed",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn extract_audio(&self, path: &str, output: &str) -> Result<(), FfmpegError> {
+        const TIMEOUT: Duration = Duration::from_secs(600); // 10 minutes
+
+        capabilities::ensure_supported(
+            &[(Component::Encoder, "pcm_s16le"), (Component::Muxer, "wav")],
+            "extract audio for subtitle generation",
+        )
+        .await?;
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args([
+            "-i",
+            path,
+            "-vn", // No video
+            "-acodec",
+            "pcm_s16le", // PCM 16-bit little-endian
+            "-ar",
+            "16000", // Sample rate 16kHz (Whisper's expected rate)
+            "-ac",
+            "1",  // Mono channel
+            "-y", // Overwrite output file
+            output,
+        ]);
+
+        let result = crate::run_with_timeout(cmd, TIMEOUT, "ffmpeg", Priority::Interactive).await?;
+
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg failed to extract audio",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn extract_cover_art(
+        &self,
+        path: &str,
+        stream_index: i64,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        let codec = if output.ends_with(".jpg") {
+            "copy"
+        } else {
+            "png"
+        };
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-i",
+            path,
+            "-map",
+            &format!("0:{}", stream_index),
+            "-c:v",
+            codec,
+            "-frames:v",
+            "1",
+            "-update",
+            "1",
+            "-y",
+            output,
+        ]);
+
+        let result =
+            crate::run_with_timeout(cmd, PROBE_TIMEOUT, "ffmpeg", Priority::Interactive).await?;
+
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg failed to extract cover art",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn extract_frame(
+        &self,
+        path: &str,
+        time: f64,
+        width: u32,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        let encoder_args: &[&str] = if output.ends_with(".webp") {
+            &["-c:v", "libwebp", "-quality", "75"]
+        } else {
+            &["-q:v", "5"]
+        };
+
+        // Seeking before `-i` jumps to the nearest keyframe and decodes from
+        // there, which is fast even hours into a file. `0:V` skips cover art.
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-ss",
+            &format!("{:.3}", time),
+            "-i",
+            path,
+            "-map",
+            "0:V:0",
+            "-frames:v",
+            "1",
+            "-vf",
+            &format!("scale='min({},iw)':-2", width),
+        ])
+        .args(encoder_args)
+        .args(["-update", "1", "-y", output]);
+
+        let result =
+            crate::run_with_timeout(cmd, PROBE_TIMEOUT, "ffmpeg", Priority::Background).await?;
+
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg failed to extract frame",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn render_sprite_sheet(
+        &self,
+        path: &str,
+        layout: SpriteLayout,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        let SpriteLayout {
+            interval,
+            tile_width,
+            tile_height,
+            columns,
+            rows,
+        } = layout;
+
+        // Only keyframes are decoded, which makes a pass over a feature film
+        // take seconds rather than minutes. Each tile shows the keyframe
+        // nearest its time, which is close enough for a seek preview.
+        let filter = format!(
+            "fps=1/{interval},scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,tile={columns}x{rows}",
+            w = tile_width,
+            h = tile_height,
+        );
+        let timeout = crate::size_scaled_timeout(path, 60, 1800);
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-skip_frame",
+            "nokey",
+            "-i",
+            path,
+            "-map",
+            "0:V:0",
+            "-vf",
+            &filter,
+            "-frames:v",
+            "1",
+            "-q:v",
+            "5",
+            "-update",
+            "1",
+            "-y",
+            output,
+        ]);
+
+        let result = crate::run_with_timeout(cmd, timeout, "ffmpeg", Priority::Background).await?;
+
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg failed to render seek previews",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn render_hover_preview(
+        &self,
+        path: &str,
+        starts: &[f64],
+        sample_secs: f64,
+        width: u32,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        const TIMEOUT: Duration = Duration::from_secs(120);
+        const FPS: u32 = 12;
+
+        let encoder_args: Vec<&str> = if output.ends_with(".webp") {
+            vec!["-c:v", "libwebp_anim", "-quality", "60", "-loop", "0"]
+        } else {
+            let caps = tokio::task::spawn_blocking(capabilities::get_capabilities)
+                .await
+                .ok()
+                .and_then(Result::ok);
+            let (encoder, args) =
+                pick_encoder(caps.as_ref(), WEBM_VIDEO_ENCODERS, "VP9/VP8/AV1", "webm")?;
+            let mut encoder_args = vec!["-c:v", encoder];
+            encoder_args.extend_from_slice(args);
+            encoder_args
+        };
+
+        // Each excerpt is its own input so FFmpeg seeks straight to it
+        // instead of decoding everything in between.
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args(["-v", "error"]);
+        for start in starts {
+            cmd.args([
+                "-ss",
+                &format!("{:.3}", start),
+                "-t",
+                &format!("{:.3}", sample_secs),
+                "-i",
+                path,
+            ]);
+        }
+
+        let count = starts.len();
+        let scaled: String = (0..count)
+            .map(|i| format!("[{i}:V:0]fps={FPS},scale={width}:-2,setsar=1[v{i}];"))
+            .collect();
+        let labels: String = (0..count).map(|i| format!("[v{i}]")).collect();
+        let filter = format!("{scaled}{labels}concat=n={count}:v=1:a=0[out]");
+
+        cmd.args(["-filter_complex", &filter, "-map", "[out]", "-an"])
+            .args(&encoder_args)
+            .args(["-y", output]);
+
+        let result = crate::run_with_timeout(cmd, TIMEOUT, "ffmpeg", Priority::Background).await?;
+
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg failed to render hover preview",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn decode_check(
+        &self,
+        path: &str,
+        on_progress: impl Fn(f64) + Send + Sync,
+    ) -> Result<DecodeCheck, FfmpegError> {
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args([
+            "-v",
+            "error",
+            "-nostats",
+            "-progress",
+            "pipe:1",
+            "-i",
+            path,
+            "-f",
+            "null",
+            "-",
+        ]);
+
+        // No timeout: decoding takes about as long as a transcode without the
+        // encoding, which for a long film is still many minutes.
+        let _permit = process_scheduler::acquire(Priority::LongRunning).await?;
+        let mut child = tokio::process::Command::from(cmd)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| FfmpegError::spawn_failed("ffmpeg", e))?;
+
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return Err(FfmpegError::new(
+                FfmpegErrorKind::Other,
+                "Failed to capture ffmpeg output",
+            ));
+        };
+        let mut progress_lines = BufReader::new(stdout).lines();
+        let mut error_lines = BufReader::new(stderr).lines();
+
+        // Errors are stamped with the last reported position, which is why
+        // both pipes are read in one loop rather than in separate tasks.
+        let mut check = DecodeCheck::default();
+        let mut stderr_text = String::new();
+        let (mut progress_done, mut errors_done) = (false, false);
+        while !(progress_done && errors_done) {
+            tokio::select! {
+                line = progress_lines.next_line(), if !progress_done => match line {
+                    Ok(Some(line)) => {
+                        let micros = line
+                            .strip_prefix("out_time_us=")
+                            .and_then(|value| value.trim().parse::<i64>().ok())
+                            .filter(|micros| *micros >= 0);
+                        if let Some(micros) = micros {
+                            check.decoded_seconds = micros as f64 / 1_000_000.0;
+                            on_progress(check.decoded_seconds);
+                        }
+                    }
+                    _ => progress_done = true,
+                },
+                line = error_lines.next_line(), if !errors_done => match line {
+                    Ok(Some(line)) => {
+                        if stderr_text.len() < 64 * 1024 {
+                            stderr_text.push_str(&line);
+                            stderr_text.push('\n');
+                        }
+                        if check.errors.len() < integrity::MAX_DECODE_ERRORS {
+                            check.errors.push(DecodeError {
+                                time: check.decoded_seconds,
+                                message: integrity::clean_error_line(&line),
+                            });
+                        } else {
+                            check.omitted_errors += 1;
+                        }
+                    }
+                    _ => errors_done = true,
+                },
+            }
+        }
+
+        let status = child.wait().await.map_err(|e| {
+            FfmpegError::new(
+                FfmpegErrorKind::Other,
+                format!("Failed to wait for ffmpeg: {}", e),
+            )
+        })?;
+
+        // Failing before anything was decoded means the file could not be
+        // opened at all, which is an error rather than a finding.
+        if !status.success() && check.decoded_seconds == 0.0 {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg failed to verify file",
+                stderr_text.as_bytes(),
+            ));
+        }
+        check.completed = status.success();
+
+        Ok(check)
+    }
+
+    async fn measure_loudness(&self, path: &str) -> Result<LoudnessMeasurement, FfmpegError> {
+        capabilities::ensure_supported(&[(Component::Filter, "loudnorm")], "measure loudness")
+            .await?;
+
+        // Only the audio is decoded, which is far faster than playback, but
+        // the whole file still has to be read.
+        let timeout = crate::size_scaled_timeout(path, 60, 1800);
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args([
+            "-hide_banner",
+            "-nostats",
+            "-i",
+            path,
+            "-map",
+            "0:a:0",
+            "-af",
+            "loudnorm=print_format=json",
+            "-f",
+            "null",
+            "-",
+        ]);
+
+        let output = crate::run_with_timeout(cmd, timeout, "ffmpeg", Priority::Background).await?;
+
+        if !output.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg failed to measure loudness",
+                &output.stderr,
+            ));
+        }
+
+        loudness::parse_loudnorm_output(&String::from_utf8_lossy(&output.stderr))
+    }
+
+    async fn transcode(
+        &self,
+        input: &str,
+        output: &str,
+        target_format: &str,
+    ) -> Result<(), FfmpegError> {
+        let caps = tokio::task::spawn_blocking(capabilities::get_capabilities)
+            .await
+            .ok()
+            .and_then(Result::ok);
+        let codecs = plan_conversion(caps.as_ref(), target_format)?;
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.arg("-i").arg(input);
+
+        match codecs {
+            Some(((video_encoder, video_args), (audio_encoder, audio_args))) => {
+                cmd.args(["-c:v", video_encoder]).args(video_args);
+                cmd.args(["-c:a", audio_encoder]).args(audio_args);
+            }
+            None => {
+                cmd.args(["-c:v", "copy", "-c:a", "copy"]);
+            }
+        }
+
+        cmd.arg("-y").arg(output);
+
+        // No timeout: a full re-encode of a long film can legitimately take hours.
+        let _permit = process_scheduler::acquire(Priority::LongRunning).await?;
+        let result = tokio::process::Command::from(cmd)
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| FfmpegError::spawn_failed("ffmpeg", e))?;
+
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg conversion failed",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn remux(&self, input: &str, output: &str, container: &str) -> Result<(), FfmpegError> {
+        let muxer = muxer_for(container)?;
+        capabilities::ensure_supported(&[(Component::Muxer, muxer)], "remux to this format")
+            .await?;
+
+        // Reads and writes the whole file, like switching audio tracks.
+        let timeout = crate::size_scaled_timeout(input, 120, 3600);
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args(["-v", "error", "-i", input])
+            .args(["-map", "0:V?", "-map", "0:a?"])
+            .args(["-c", "copy", "-y", output]);
+
+        let result = crate::run_with_timeout(cmd, timeout, "ffmpeg", Priority::LongRunning).await?;
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg remux failed",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn transcode_audio(
+        &self,
+        input: &str,
+        output: &str,
+        container: &str,
+    ) -> Result<(), FfmpegError> {
+        let muxer = muxer_for(container)?;
+        capabilities::ensure_supported(&[(Component::Muxer, muxer)], "convert to this format")
+            .await?;
+        let caps = tokio::task::spawn_blocking(capabilities::get_capabilities)
+            .await
+            .ok()
+            .and_then(Result::ok);
+        let (encoder, encoder_args) = if container == "webm" {
+            pick_encoder(caps.as_ref(), WEBM_AUDIO_ENCODERS, "Opus/Vorbis", container)?
+        } else {
+            pick_encoder(caps.as_ref(), MP4_AUDIO_ENCODERS, "AAC", container)?
+        };
+
+        // Audio encodes at dozens of times playback speed, but still has to
+        // read past all the video.
+        let timeout = crate::size_scaled_timeout(input, 300, 7200);
+
+        let mut cmd = crate::get_ffmpeg_command();
+        cmd.args(["-v", "error", "-i", input])
+            .args(["-map", "0:V?", "-map", "0:a?"])
+            .args(["-c:v", "copy", "-c:a", encoder])
+            .args(encoder_args)
+            .args(["-y", output]);
+
+        let result = crate::run_with_timeout(cmd, timeout, "ffmpeg", Priority::LongRunning).await?;
+        if !result.status.success() {
+            return Err(FfmpegError::from_stderr(
+                "FFmpeg audio conversion failed",
+                &result.stderr,
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// Marker file next to the executable that switches Glucose into portable mode.
+const PORTABLE_MARKER: &str = "portable.flag";
+
+// Folder beside the executable that holds all app data in portable mode.
+const PORTABLE_DATA_DIR: &str = "glucose-data";
+
+// Name of the per-app subdirectory created under each XDG base directory.
+#[cfg(target_os = "linux")]
+const APP_DIR_NAME: &str = "glucose";
+
+// Data directory beside the executable when `portable.flag` sits next to it.
+// Checked once per run so every path agrees for the lifetime of the process.
+pub(crate) fn portable_dir() -> Option<&'static Path> {
+    static PORTABLE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    PORTABLE_DIR
+        .get_or_init(|| {
+            let exe = std::env::current_exe().ok()?;
+            portable_dir_in(exe.parent()?)
+        })
+        .as_deref()
+}
+
+fn portable_dir_in(exe_dir: &Path) -> Option<PathBuf> {
+    exe_dir
+        .join(PORTABLE_MARKER)
+        .is_file()
+        .then(|| exe_dir.join(PORTABLE_DATA_DIR))
+}
+
+// Everything the data locations are derived from. Resolved from the running
+// process by `current`, and built by hand in tests.
+struct BaseDirs {
+    // Data directory beside the executable in portable mode.
+    portable: Option<PathBuf>,
+    glucose_home: Option<OsString>,
+    home: Option<PathBuf>,
+    #[cfg(target_os = "linux")]
+    xdg_config: Option<PathBuf>,
+    #[cfg(target_os = "linux")]
+    xdg_state: Option<PathBuf>,
+    #[cfg(target_os = "linux")]
+    xdg_data: Option<PathBuf>,
+    #[cfg(target_os = "linux")]
+    xdg_cache: Option<PathBuf>,
+}
+
+impl BaseDirs {
+    fn current() -> Self {
+        BaseDirs {
+            portable: portable_dir().map(Path::to_path_buf),
+            glucose_home: std::env::var_os("GLUCOSE_HOME"),
+            home: dirs::home_dir(),
+            #[cfg(target_os = "linux")]
+            xdg_config: dirs::config_dir(),
+            #[cfg(target_os = "linux")]
+            xdg_state: dirs::state_dir(),
+            #[cfg(target_os = "linux")]
+            xdg_data: dirs::data_dir(),
+            #[cfg(target_os = "linux")]
+            xdg_cache: dirs::cache_dir(),
+        }
+    }
+
+    // Portable mode and GLUCOSE_HOME both relocate all app data into a single
+    // directory, laid out the same way as the legacy ~/.glucose folder.
+    // Portable mode wins so a USB install never picks up the host machine's
+    // environment.
+    fn home_override(&self) -> Option<PathBuf> {
+        if let Some(dir) = &self.portable {
+            return Some(dir.clone());
+        }
+        self.glucose_home
+            .as_ref()
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    }
+
+    fn legacy_dir(&self) -> Result<PathBuf, String> {
+        let home = self.home.as_ref().ok_or("Could not find home directory")?;
+        Ok(home.join(".glucose"))
+    }
+
+    fn config_dir(&self) -> Result<PathBuf, String> {
+        if let Some(home) = self.home_override() {
+            return Ok(home);
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            xdg_app_dir(self.xdg_config.clone())
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.legacy_dir()
+        }
+    }
+
+    fn state_dir(&self) -> Result<PathBuf, String> {
+        if let Some(home) = self.home_override() {
+            return Ok(home);
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            xdg_app_dir(self.xdg_state.clone())
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.legacy_dir()
+        }
+    }
+
+    fn models_dir(&self) -> Result<PathBuf, String> {
+        if let Some(home) = self.home_override() {
+            return Ok(home.join("models"));
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            Ok(xdg_app_dir(self.xdg_data.clone())?.join("models"))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.home
+                .as_ref()
+                .map(|home| home.join(".whisper").join("models"))
+                .ok_or_else(|| "Could not find home directory".to_string())
+        }
+    }
+
+    fn managed_ffmpeg_dir(&self) -> Result<PathBuf, String> {
+        if let Some(home) = self.home_override() {
+            return Ok(home.join("ffmpeg"));
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            Ok(xdg_app_dir(self.xdg_data.clone())?.join("ffmpeg"))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(self.legacy_dir()?.join("ffmpeg"))
+        }
+    }
+
+    fn cache_dir(&self) -> Result<PathBuf, String> {
+        if let Some(home) = self.home_override() {
+            return Ok(home.join("cache"));
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            xdg_app_dir(self.xdg_cache.clone())
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(self.legacy_dir()?.join("cache"))
+        }
+    }
+
+    // Moves config and watch history out of ~/.glucose when the resolved
+    // locations differ. A file is only moved if the destination does not exist
+    // yet.
+    fn migrate_legacy_data(&self) {
+        // A portable install must not pull the host machine's data onto the
+        // stick.
+        if self.portable.is_some() {
+            return;
+        }
+
+        let Ok(legacy) = self.legacy_dir() else {
+            return;
+        };
+
+        // Files that used to live in ~/.glucose, paired with the directory
+        // they now belong in.
+        let legacy_files = [
+            ("config.json", self.config_dir()),
+            ("watch_progress.json", self.state_dir()),
+        ];
+
+        for (file_name, target_dir) in legacy_files {
+            let Ok(target_dir) = target_dir else {
+                continue;
+            };
+            let source = legacy.join(file_name);
+            let target = target_dir.join(file_name);
+            if source == target || !source.is_file() || target.exists() {
+                continue;
+            }
+
+            let _result = move_file(&source, &target);
+            #[cfg(debug_assertions)]
+            match &_result {
+                Ok(()) => println!(
+                    "[Paths] Migrated {} to {}",
+                    source.display(),
+                    target.display()
+                ),
+                Err(err) => eprintln!(
+                    "[Paths] Failed to migrate {} to {}: {}",
+                    source.display(),
+                    target.display(),
+                    err
+                ),
+            }
+        }
+    }
+}
+
+// Legacy Whisper model location shared with other whisper.cpp tools. It is
+// still searched for models but never written to on Linux.
+pub(crate) fn legacy_models_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".whisper").join("models"))
+}
+
+#[cfg(target_os = "linux")]
+fn xdg_app_dir(base: Option<PathBuf>) -> Result<PathBuf, String> {
+    base.map(|dir| dir.join(APP_DIR_NAME))
+        .ok_or_else(|| "Could not find home directory".to_string())
+}
+
+// Directory holding config.json: $XDG_CONFIG_HOME/glucose on Linux,
+// ~/.glucose elsewhere.
+pub(crate) fn config_dir() -> Result<PathBuf, String> {
+    BaseDirs::current().config_dir()
+}
+
+// Directory holding watch history and its lock file: $XDG_STATE_HOME/glucose
+// on Linux, ~/.glucose elsewhere.
+pub(crate) fn state_dir() -> Result<PathBuf, String> {
+    BaseDirs::current().state_dir()
+}
+
+// Directory new Whisper models are downloaded into: $XDG_DATA_HOME/glucose/models
+// on Linux, ~/.whisper/models elsewhere.
+pub(crate) fn models_dir() -> Result<PathBuf, String> {
+    BaseDirs::current().models_dir()
+}
+
+// Directory `install_managed_ffmpeg` places ffmpeg/ffprobe in:
+// $XDG_DATA_HOME/glucose/ffmpeg on Linux, ~/.glucose/ffmpeg elsewhere.
+pub(crate) fn managed_ffmpeg_dir() -> Result<PathBuf, String> {
+    BaseDirs::current().managed_ffmpeg_dir()
+}
+
+// Directory for regenerable data such as the probe cache:
+// $XDG_CACHE_HOME/glucose on Linux, ~/.glucose/cache elsewhere.
+pub(crate) fn cache_dir() -> Result<PathBuf, String> {
+    BaseDirs::current().cache_dir()
+}
+
+// Whether the legacy ~/.whisper/models folder should still be searched. An
+// explicit GLUCOSE_HOME or portable mode keeps the app fully self-contained.
+pub(crate) fn search_legacy_models() -> bool {
+    BaseDirs::current().home_override().is_none()
+}
+
+// Directory for scratch files such as remuxed audio tracks and extracted
+// Whisper audio. In portable mode this stays beside the executable, falling
+// back to the system temp folder if that location is not writable.
+pub(crate) fn temp_dir() -> PathBuf {
+    temp_dir_in(portable_dir())
+}
+
+fn temp_dir_in(portable: Option<&Path>) -> PathBuf {
+    if let Some(dir) = portable {
+        let temp = dir.join("tmp");
+        if fs::create_dir_all(&temp).is_ok() {
+            return temp;
+        }
+    }
+    std::env::temp_dir()
+}
+
+fn move_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // rename fails across filesystems (e.g. GLUCOSE_HOME on another drive),
+    // so fall back to copy + delete.
+    if fs::rename(source, target).is_err() {
+        fs::copy(source, target)?;
+        fs::remove_file(source)?;
+    }
+    Ok(())
+}
+
+// One-time move of config and watch history out of ~/.glucose when the
+// resolved locations differ. Running this on every launch is harmless.
+pub(crate) fn migrate_legacy_data() {
+    BaseDirs::current().migrate_legacy_data();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A home directory and XDG base directories under `root`, with no
+    // overrides set.
+    fn base_dirs(root: &Path) -> BaseDirs {
+        BaseDirs {
+            portable: None,
+            glucose_home: None,
+            home: Some(root.join("home")),
+            #[cfg(target_os = "linux")]
+            xdg_config: Some(root.join("xdg/config")),
+            #[cfg(target_os = "linux")]
+            xdg_state: Some(root.join("xdg/state")),
+            #[cfg(target_os = "linux")]
+            xdg_data: Some(root.join("xdg/data")),
+            #[cfg(target_os = "linux")]
+            xdg_cache: Some(root.join("xdg/cache")),
+        }
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("paths-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn glucose_home_relocates_everything() {
+        let dirs = BaseDirs {
+            glucose_home: Some("/data/glucose".into()),
+            ..base_dirs(Path::new("/r"))
+        };
+        let home = PathBuf::from("/data/glucose");
+
+        assert_eq!(dirs.config_dir(), Ok(home.clone()));
+        assert_eq!(dirs.state_dir(), Ok(home.clone()));
+        assert_eq!(dirs.models_dir(), Ok(home.join("models")));
+        assert_eq!(dirs.managed_ffmpeg_dir(), Ok(home.join("ffmpeg")));
+        assert_eq!(dirs.cache_dir(), Ok(home.join("cache")));
+    }
+
+    #[test]
+    fn empty_glucose_home_is_ignored() {
+        let dirs = BaseDirs {
+            glucose_home: Some("".into()),
+            ..base_dirs(Path::new("/r"))
+        };
+        assert_eq!(dirs.home_override(), None);
+        assert_eq!(dirs.config_dir(), base_dirs(Path::new("/r")).config_dir());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn linux_follows_xdg_base_dirs() {
+        let dirs = base_dirs(Path::new("/r"));
+
+        assert_eq!(
+            dirs.config_dir(),
+            Ok(PathBuf::from("/r/xdg/config/glucose"))
+        );
+        assert_eq!(dirs.state_dir(), Ok(PathBuf::from("/r/xdg/state/glucose")));
+        assert_eq!(
+            dirs.models_dir(),
+            Ok(PathBuf::from("/r/xdg/data/glucose/models"))
+        );
+        assert_eq!(
+            dirs.managed_ffmpeg_dir(),
+            Ok(PathBuf::from("/r/xdg/data/glucose/ffmpeg"))
+        );
+        assert_eq!(dirs.cache_dir(), Ok(PathBuf::from("/r/xdg/cache/glucose")));
+    }
+
+    #[test]
+    fn marker_beside_the_executable_enables_portable_mode() {
+        let exe_dir = scratch("portable-marker");
+        assert_eq!(portable_dir_in(&exe_dir), None);
+
+        fs::write(exe_dir.join(PORTABLE_MARKER), "").unwrap();
+        assert_eq!(
+            portable_dir_in(&exe_dir),
+            Some(exe_dir.join(PORTABLE_DATA_DIR))
+        );
+
+        let _ = fs::remove_dir_all(&exe_dir);
+    }
+
+    #[test]
+    fn portable_mode_beats_glucose_home() {
+        let dirs = BaseDirs {
+            portable: Some(PathBuf::from("/usb/glucose-data")),
+            glucose_home: Some("/data/glucose".into()),
+            ..base_dirs(Path::new("/r"))
+        };
+        assert_eq!(dirs.config_dir(), Ok(PathBuf::from("/usb/glucose-data")));
+        assert_eq!(
+            dirs.cache_dir(),
+            Ok(PathBuf::from("/usb/glucose-data/cache"))
+        );
+    }
+
+    #[test]
+    fn portable_mode_skips_migration() {
+        let root = scratch("portable-migrate");
+        let legacy = root.join("home/.glucose");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("config.json"), "host config").unwrap();
+        let portable = root.join(PORTABLE_DATA_DIR);
+
+        let dirs = BaseDirs {
+            portable: Some(portable.clone()),
+            ..base_dirs(&root)
+        };
+        dirs.migrate_legacy_data();
+
+        assert!(legacy.join("config.json").exists());
+        assert!(!portable.join("config.json").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn temp_dir_stays_portable_unless_unwritable() {
+        let root = scratch("portable-temp");
+        assert_eq!(temp_dir_in(Some(&root)), root.join("tmp"));
+
+        // A file where the data directory should be makes it unwritable.
+        let blocked = root.join("blocked");
+        fs::write(&blocked, "").unwrap();
+        assert_eq!(temp_dir_in(Some(&blocked)), std::env::temp_dir());
+        assert_eq!(temp_dir_in(None), std::env::temp_dir());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn migration_moves_files_only_to_free_targets() {
+        let root = scratch("migrate");
+        let legacy = root.join("home/.glucose");
+        let target = root.join("relocated");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(legacy.join("config.json"), "legacy config").unwrap();
+        fs::write(legacy.join("watch_progress.json"), "legacy progress").unwrap();
+        fs::write(target.join("watch_progress.json"), "current progress").unwrap();
+
+        let dirs = BaseDirs {
+            glucose_home: Some(target.clone().into()),
+            ..base_dirs(&root)
+        };
+        dirs.migrate_legacy_data();
+
+        assert!(!legacy.join("config.json").exists());
+        assert_eq!(
+            fs::read_to_string(target.join("config.json")).unwrap(),
+            "legacy config"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("watch_progress.json")).unwrap(),
+            "current progress"
+        );
+        assert!(legacy.join("watch_progress.json").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
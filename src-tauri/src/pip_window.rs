@@ -1,8 +1,4 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::fs;
-use std::io::Write;
-use std::path::PathBuf;
 use std::sync::{Mutex, OnceLock};
 use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, WebviewWindow};
 
@@ -50,7 +46,7 @@ struct AppConstants {
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
-struct PipWindowLayout {
+pub(crate) struct PipWindowLayout {
     width: u32,
     height: u32,
     x: i32,
@@ -299,6 +295,38 @@ pub(crate) fn save_pip_window_layout(app_handle: AppHandle) -> Result<(), String
     Ok(())
 }
 
+// Moves an active PiP window to the layout stored in config.json, used when
+// the file is edited outside the app. No-op when PiP is not active.
+pub(crate) fn apply_saved_pip_layout(app_handle: &AppHandle) -> Result<(), String> {
+    if !is_pip_active() {
+        return Ok(());
+    }
+    let Some(layout) = load_saved_pip_layout()? else {
+        return Ok(());
+    };
+
+    let window = main_window(app_handle)?;
+    let config = get_pip_constants()?;
+    let work_area = work_area_for_window(&window);
+    let size = normalize_pip_size(layout.width, layout.height, None, &config, &work_area);
+    let position = snap_and_clamp_position(
+        PhysicalPosition::new(layout.x, layout.y),
+        size,
+        &work_area,
+        &config,
+    );
+
+    window
+        .set_size(size)
+        .map_err(|e| format!("Failed to set PiP size: {}", e))?;
+    window
+        .set_position(position)
+        .map_err(|e| format!("Failed to set PiP position: {}", e))?;
+    update_pip_size(size);
+
+    Ok(())
+}
+
 fn main_window(app_handle: &AppHandle) -> Result<WebviewWindow, String> {
     app_handle
         .get_webview_window("main")
@@ -503,74 +531,18 @@ fn clamp_i32(value: i32, min: i32, max: i32) -> i32 {
     value.clamp(min, max)
 }
 
-fn user_config_path() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    Ok(home.join(".glucose").join("config.json"))
-}
-
 fn load_saved_pip_layout() -> Result<Option<PipWindowLayout>, String> {
-    let config_file = user_config_path()?;
-    let _guard = crate::CONFIG_MUTEX
-        .lock()
-        .unwrap_or_else(|e| e.into_inner());
-
-    if !config_file.exists() {
-        return Ok(None);
-    }
-
-    let content =
-        fs::read_to_string(&config_file).map_err(|e| format!("Failed to read config: {}", e))?;
-    let config: Value = match serde_json::from_str(&content) {
-        Ok(v) => v,
-        Err(_) => return Ok(None),
-    };
-
-    Ok(config
-        .get("pip_window")
-        .and_then(|value| serde_json::from_value(value.clone()).ok()))
+    // A missing or unreadable layout just falls back to the default PiP
+    // placement rather than blocking PiP mode.
+    Ok(crate::settings::load_settings()
+        .ok()
+        .and_then(|settings| settings.pip_window))
 }
 
 fn save_pip_layout(layout: PipWindowLayout) -> Result<(), String> {
-    let config_file = user_config_path()?;
-    let config_dir = config_file
-        .parent()
-        .ok_or_else(|| "Could not get config directory".to_string())?;
-
-    fs::create_dir_all(config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
-
-    let _guard = crate::CONFIG_MUTEX
-        .lock()
-        .unwrap_or_else(|e| e.into_inner());
-
-    let mut config: Value = if config_file.exists() {
-        let content = fs::read_to_string(&config_file)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?
-    } else {
-        serde_json::json!({})
-    };
-
-    let config_object = config
-        .as_object_mut()
-        .ok_or_else(|| "Config root must be a JSON object".to_string())?;
-    config_object.insert("pip_window".to_string(), serde_json::json!(layout));
-
-    let content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-    let temp_file = config_file.with_extension("json.tmp");
-    {
-        let mut file = fs::File::create(&temp_file)
-            .map_err(|e| format!("Failed to create temp config: {}", e))?;
-        file.write_all(content.as_bytes())
-            .map_err(|e| format!("Failed to write temp config: {}", e))?;
-        file.sync_all()
-            .map_err(|e| format!("Failed to sync temp config: {}", e))?;
-    }
-    if let Err(e) = fs::rename(&temp_file, &config_file) {
-        let _ = fs::remove_file(&temp_file);
-        return Err(format!("Failed to replace config: {}", e));
-    }
-
+    crate::settings::modify_settings(|settings| {
+        settings.pip_window = Some(layout);
+        Ok(())
+    })?;
     Ok(())
 }
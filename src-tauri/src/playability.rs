@@ -0,0 +1,423 @@
+use crate::ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use crate::media_toolkit::{CliToolkit, MediaToolkit, ProbedStream, StreamKind};
+use crate::probe_cache::CachedToolkit;
+use serde::Serialize;
+use std::path::Path;
+
+// What the platform webview can decode without help. Names are file
+// extensions for containers and ffprobe codec names for streams. The lists
+// are deliberately conservative: a codec that only works with optional
+// hardware or OS extensions (HEVC on Windows, AV1 on older Macs) is left out,
+// because a false "plays directly" is a black screen for the user.
+struct WebviewSupport {
+    containers: &'static [&'static str],
+    video: &'static [&'static str],
+    audio: &'static [&'static str],
+}
+
+// WebView2 (Chromium).
+#[cfg(target_os = "windows")]
+const WEBVIEW: WebviewSupport = WebviewSupport {
+    containers: &["mp4", "m4v", "mov", "webm", "mkv"],
+    video: &["h264", "vp8", "vp9", "av1"],
+    audio: &["aac", "mp3", "opus", "vorbis", "flac"],
+};
+
+// WKWebView (Safari).
+#[cfg(target_os = "macos")]
+const WEBVIEW: WebviewSupport = WebviewSupport {
+    containers: &["mp4", "m4v", "mov", "webm"],
+    video: &["h264", "hevc", "vp9"],
+    audio: &["aac", "mp3", "alac", "flac", "opus", "ac3", "eac3"],
+};
+
+// WebKitGTK, which decodes through the installed GStreamer plugins. H.264 and
+// AAC need gst-libav, which every mainstream distribution ships by default.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const WEBVIEW: WebviewSupport = WebviewSupport {
+    containers: &["mp4", "m4v", "mov", "webm", "mkv", "ogg", "ogv"],
+    video: &["h264", "vp8", "vp9", "av1", "theora"],
+    audio: &["aac", "mp3", "opus", "vorbis", "flac"],
+};
+
+// Uncompressed audio as stored in WAV files. Every webview plays these on
+// their own, but not necessarily inside a video container.
+const WAV_AUDIO: &[&str] = &["pcm_u8", "pcm_s16le", "pcm_s24le", "pcm_f32le"];
+
+// Containers a fix can produce, in order of preference, with the codecs each
+// can hold.
+const OUTPUT_CONTAINERS: &[(&str, &[&str], &[&str])] = &[
+    (
+        "mp4",
+        &["h264", "hevc", "av1", "vp9"],
+        &["aac", "mp3", "opus", "flac", "alac", "ac3", "eac3"],
+    ),
+    ("webm", &["vp8", "vp9", "av1"], &["opus", "vorbis"]),
+    (
+        "mkv",
+        &["h264", "hevc", "av1", "vp8", "vp9", "theora"],
+        &[
+            "aac", "mp3", "opus", "vorbis", "flac", "alac", "ac3", "eac3",
+        ],
+    ),
+];
+
+// Rough processing speed of each fix as a multiple of playback speed on a
+// typical desktop. Only used to give the UI an order of magnitude.
+const REMUX_SPEED: f64 = 100.0;
+const AUDIO_TRANSCODE_SPEED: f64 = 40.0;
+const FULL_TRANSCODE_SPEED: f64 = 1.5;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Verdict {
+    Direct,
+    // Codecs are fine, only the container is not: stream copy.
+    Remux,
+    // Video can be copied, audio has to be re-encoded.
+    TranscodeAudio,
+    Transcode,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PlayabilityReport {
+    verdict: Verdict,
+    container: String,
+    video_codec: Option<String>,
+    audio_codec: Option<String>,
+    // What the webview cannot handle, e.g. "AVI container", "DTS audio".
+    unsupported: Vec<String>,
+    // Container the fix should write. None when the file plays directly.
+    target_container: Option<String>,
+    // Seconds the fix is expected to take, when the duration is known.
+    estimated_seconds: Option<f64>,
+}
+
+// The stream the webview would pick: the default one, else the first.
+// Cover art is not a video track.
+fn main_stream(streams: Vec<ProbedStream>) -> Option<ProbedStream> {
+    let mut streams: Vec<ProbedStream> = streams
+        .into_iter()
+        .filter(|stream| !stream.is_attached_pic)
+        .collect();
+    let default = streams.iter().position(|stream| stream.is_default);
+    match default {
+        Some(index) => Some(streams.swap_remove(index)),
+        None => streams.into_iter().next(),
+    }
+}
+
+fn supports(list: &[&str], codec: Option<&str>) -> bool {
+    codec.is_none_or(|codec| list.contains(&codec))
+}
+
+// First output container the webview plays that can hold the video codec and
+// the audio codec `audio_for` picks for that container.
+fn target_container<'a>(
+    video: Option<&str>,
+    audio_for: impl Fn(&'static str) -> Option<&'a str>,
+) -> Option<&'static str> {
+    OUTPUT_CONTAINERS
+        .iter()
+        .find(|(name, video_codecs, audio_codecs)| {
+            WEBVIEW.containers.contains(name)
+                && supports(video_codecs, video)
+                && supports(audio_codecs, audio_for(name))
+        })
+        .map(|(name, _, _)| *name)
+}
+
+// The audio codec a fix re-encodes to for a given output container.
+fn transcoded_audio(container: &str) -> &'static str {
+    match container {
+        "webm" => "opus",
+        _ => "aac",
+    }
+}
+
+fn plan(
+    container: &str,
+    video_codec: Option<&str>,
+    audio_codec: Option<&str>,
+) -> PlayabilityReport {
+    // Audio-only files play in an audio element, which goes by the codec: the
+    // container table lists video containers and does not apply to them.
+    let audio_only = video_codec.is_none();
+    let container_ok = audio_only || WEBVIEW.containers.contains(&container);
+    let video_ok = supports(WEBVIEW.video, video_codec);
+    let audio_ok =
+        supports(WEBVIEW.audio, audio_codec) || (audio_only && supports(WAV_AUDIO, audio_codec));
+
+    let mut unsupported = Vec::new();
+    if !container_ok {
+        unsupported.push(format!("{} container", container.to_uppercase()));
+    }
+    if let Some(codec) = video_codec.filter(|_| !video_ok) {
+        unsupported.push(format!("{} video", codec.to_uppercase()));
+    }
+    if let Some(codec) = audio_codec.filter(|_| !audio_ok) {
+        unsupported.push(format!("{} audio", codec.to_uppercase()));
+    }
+
+    // A full transcode writes H.264/AAC MP4, which every webview plays.
+    let (verdict, target) = if !video_ok {
+        (Verdict::Transcode, Some("mp4"))
+    } else if !audio_ok {
+        match target_container(video_codec, |name| Some(transcoded_audio(name))) {
+            // Keeps an audio extension so the player still treats it as
+            // music.
+            Some("mp4") if audio_only => (Verdict::TranscodeAudio, Some("m4a")),
+            Some(name) => (Verdict::TranscodeAudio, Some(name)),
+            None => (Verdict::Transcode, Some("mp4")),
+        }
+    } else if !container_ok {
+        match target_container(video_codec, |_| audio_codec) {
+            Some(name) => (Verdict::Remux, Some(name)),
+            None => (Verdict::Transcode, Some("mp4")),
+        }
+    } else {
+        (Verdict::Direct, None)
+    };
+
+    PlayabilityReport {
+        verdict,
+        container: container.to_string(),
+        video_codec: video_codec.map(str::to_string),
+        audio_codec: audio_codec.map(str::to_string),
+        unsupported,
+        target_container: target.map(str::to_string),
+        estimated_seconds: None,
+    }
+}
+
+pub(crate) async fn playability<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+) -> Result<PlayabilityReport, FfmpegError> {
+    let container = Path::new(video_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("unknown")
+        .to_lowercase();
+
+    let video = main_stream(toolkit.probe_streams(video_path, StreamKind::Video).await?);
+    let audio = main_stream(toolkit.probe_streams(video_path, StreamKind::Audio).await?);
+
+    let mut report = plan(
+        &container,
+        video.as_ref().and_then(|s| s.codec_name.as_deref()),
+        audio.as_ref().and_then(|s| s.codec_name.as_deref()),
+    );
+
+    let speed = match report.verdict {
+        Verdict::Direct => None,
+        Verdict::Remux => Some(REMUX_SPEED),
+        Verdict::TranscodeAudio => Some(AUDIO_TRANSCODE_SPEED),
+        Verdict::Transcode => Some(FULL_TRANSCODE_SPEED),
+    };
+    if let Some(speed) = speed {
+        report.estimated_seconds = toolkit
+            .probe_duration(video_path)
+            .await
+            .ok()
+            .map(|duration| duration / speed);
+    }
+
+    #[cfg(debug_assertions)]
+    println!(
+        "[Playability] {}: {:?} ({})",
+        video_path,
+        report.verdict,
+        report.unsupported.join(", ")
+    );
+
+    Ok(report)
+}
+
+// Whether the webview can play a file as-is and, if not, the cheapest way to
+// fix it, so the UI can offer a remux or transcode before the user sees a
+// black screen.
+#[tauri::command]
+pub(crate) async fn check_playability(
+    video_path: String,
+) -> Result<PlayabilityReport, FfmpegError> {
+    playability(&CachedToolkit::new(CliToolkit), &video_path).await
+}
+
+// Applies the fix `playability` recommends, writing `<name>_converted.<ext>`
+// next to the original. Returns the new file's path.
+pub(crate) async fn fix<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+) -> Result<String, FfmpegError> {
+    let report = playability(toolkit, video_path).await?;
+    let Some(container) = report.target_container.as_deref() else {
+        return Err(FfmpegError::new(
+            FfmpegErrorKind::Other,
+            "This file already plays directly",
+        ));
+    };
+
+    let path = Path::new(video_path);
+    let stem = path
+        .file_stem()
+        .ok_or_else(|| FfmpegError::new(FfmpegErrorKind::FileNotFound, "Invalid video path"))?;
+    let output = path
+        .with_file_name(format!(
+            "{}_converted.{}",
+            stem.to_string_lossy(),
+            container
+        ))
+        .to_string_lossy()
+        .to_string();
+
+    let result = match report.verdict {
+        Verdict::Remux => toolkit.remux(video_path, &output, container).await,
+        Verdict::TranscodeAudio => {
+            toolkit
+                .transcode_audio(video_path, &output, container)
+                .await
+        }
+        Verdict::Transcode => toolkit.transcode(video_path, &output, container).await,
+        Verdict::Direct => Ok(()),
+    };
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_file(&output).await;
+        return Err(e);
+    }
+
+    Ok(output)
+}
+
+// Runs the cheapest fix for a file the webview cannot play as-is.
+#[tauri::command]
+pub(crate) async fn fix_playability(video_path: String) -> Result<String, FfmpegError> {
+    fix(&CachedToolkit::new(CliToolkit), &video_path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_toolkit::FakeToolkit;
+
+    fn stream(codec: &str) -> ProbedStream {
+        ProbedStream {
+            codec_name: Some(codec.to_string()),
+            ..ProbedStream::default()
+        }
+    }
+
+    #[test]
+    fn playable_codecs_in_a_foreign_container_are_remuxed() {
+        let report = plan("avi", Some("h264"), Some("mp3"));
+        assert_eq!(report.verdict, Verdict::Remux);
+        assert_eq!(report.target_container.as_deref(), Some("mp4"));
+        assert_eq!(report.unsupported, ["AVI container"]);
+    }
+
+    #[test]
+    fn unsupported_audio_is_transcoded_alone() {
+        let report = plan("mp4", Some("h264"), Some("dts"));
+        assert_eq!(report.verdict, Verdict::TranscodeAudio);
+        assert_eq!(report.target_container.as_deref(), Some("mp4"));
+        assert_eq!(report.unsupported, ["DTS audio"]);
+    }
+
+    #[test]
+    fn unsupported_video_needs_a_full_transcode() {
+        let report = plan("wmv", Some("wmv3"), Some("wmav2"));
+        assert_eq!(report.verdict, Verdict::Transcode);
+        assert_eq!(report.target_container.as_deref(), Some("mp4"));
+        assert_eq!(report.unsupported.len(), 3);
+    }
+
+    #[test]
+    fn audio_files_are_judged_by_codec() {
+        for (container, codec) in [
+            ("mp3", "mp3"),
+            ("flac", "flac"),
+            ("wav", "pcm_s16le"),
+            ("m4a", "aac"),
+            ("opus", "opus"),
+        ] {
+            let report = plan(container, None, Some(codec));
+            assert_eq!(report.verdict, Verdict::Direct, "{}", container);
+            assert!(report.unsupported.is_empty(), "{}", container);
+        }
+    }
+
+    #[test]
+    fn unplayable_audio_files_become_m4a() {
+        let report = plan("wma", None, Some("wmav2"));
+        assert_eq!(report.verdict, Verdict::TranscodeAudio);
+        assert_eq!(report.target_container.as_deref(), Some("m4a"));
+        assert_eq!(report.unsupported, ["WMAV2 audio"]);
+    }
+
+    #[test]
+    fn pcm_is_only_trusted_outside_video_files() {
+        let report = plan("mp4", Some("h264"), Some("pcm_s16le"));
+        assert_eq!(report.verdict, Verdict::TranscodeAudio);
+    }
+
+    #[test]
+    fn cover_art_is_not_the_main_video_stream() {
+        let cover = ProbedStream {
+            is_attached_pic: true,
+            ..stream("mjpeg")
+        };
+        assert!(main_stream(vec![cover.clone()]).is_none());
+
+        let default = ProbedStream {
+            is_default: true,
+            ..stream("hevc")
+        };
+        let main = main_stream(vec![cover, stream("h264"), default]).unwrap();
+        assert_eq!(main.codec_name.as_deref(), Some("hevc"));
+    }
+
+    #[tokio::test]
+    async fn report_estimates_time_from_duration() {
+        let path = "/videos/film.avi";
+        let toolkit = FakeToolkit::new()
+            .with_streams(path, StreamKind::Video, vec![stream("h264")])
+            .with_streams(path, StreamKind::Audio, vec![stream("aac")])
+            .with_duration(path, 600.0);
+
+        let report = playability(&toolkit, path).await.unwrap();
+        assert_eq!(report.verdict, Verdict::Remux);
+        assert_eq!(report.estimated_seconds, Some(600.0 / REMUX_SPEED));
+    }
+
+    #[tokio::test]
+    async fn fix_runs_the_recommended_operation() {
+        let dir = std::env::temp_dir().join(format!("playability-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("film.mkv").to_string_lossy().to_string();
+        let toolkit = FakeToolkit::new()
+            .with_streams(&path, StreamKind::Video, vec![stream("h264")])
+            .with_streams(&path, StreamKind::Audio, vec![stream("truehd")])
+            .with_duration(&path, 60.0);
+
+        let output = fix(&toolkit, &path).await.unwrap();
+        let expected = dir.join("film_converted.mp4").to_string_lossy().to_string();
+        assert_eq!(output, expected);
+        assert!(toolkit
+            .calls()
+            .contains(&format!("transcode_audio {} {} mp4", path, expected)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn fix_refuses_files_that_play_directly() {
+        let path = "/videos/film.mp4";
+        let toolkit = FakeToolkit::new()
+            .with_streams(path, StreamKind::Video, vec![stream("h264")])
+            .with_streams(path, StreamKind::Audio, vec![stream("aac")]);
+
+        assert!(fix(&toolkit, path).await.is_err());
+        assert!(!toolkit.calls().iter().any(|call| call.starts_with("remux")));
+    }
+}
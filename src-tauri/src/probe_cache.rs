@@ -0,0 +1,546 @@
+use crate::chapters::Chapter;
+use crate::ffmpeg_error::FfmpegError;
+use crate::integrity::DecodeCheck;
+use crate::loudness::LoudnessMeasurement;
+use crate::media_info::MediaInfo;
+use crate::media_toolkit::{MediaToolkit, ProbedStream, StreamKind};
+use crate::seek_preview::SpriteLayout;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime};
+
+const CACHE_FILE_NAME: &str = "probe_cache.json";
+
+// Bumped whenever a stored field is renamed or retyped; older files are
+// discarded. New fields take `#[serde(default)]` instead, so entries written
+// before them still load.
+const CACHE_VERSION: u32 = 1;
+
+// Least recently used entries beyond this are dropped when the cache is saved.
+const MAX_ENTRIES: usize = 5000;
+
+// Writes are batched: a library scan probes hundreds of files in a row, and
+// rewriting the whole file after each one would cost more than the probes.
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+// Size and modification time at probe time. A change in either means the
+// file was replaced or edited and everything stored for it is stale.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+struct FileStamp {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct CacheEntry {
+    stamp: FileStamp,
+    duration: Option<f64>,
+    streams: HashMap<StreamKind, Vec<ProbedStream>>,
+    chapters: Option<Vec<Chapter>>,
+    #[serde(default)]
+    media_info: Option<MediaInfo>,
+    #[serde(default)]
+    decode_check: Option<DecodeCheck>,
+    #[serde(default)]
+    loudness: Option<LoudnessMeasurement>,
+    last_used: SystemTime,
+}
+
+impl CacheEntry {
+    fn new(stamp: FileStamp) -> Self {
+        CacheEntry {
+            stamp,
+            duration: None,
+            streams: HashMap::new(),
+            chapters: None,
+            media_info: None,
+            decode_check: None,
+            loudness: None,
+            last_used: SystemTime::now(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    version: u32,
+    // Keyed by canonical path so different spellings of one file share an
+    // entry.
+    entries: HashMap<String, CacheEntry>,
+}
+
+// The cache behind `CachedToolkit::new`, saved under the app's cache
+// directory.
+static PROBE_CACHE: OnceLock<Arc<ProbeCache>> = OnceLock::new();
+
+fn cache_file_path() -> Result<PathBuf, String> {
+    Ok(crate::paths::cache_dir()?.join(CACHE_FILE_NAME))
+}
+
+pub(crate) struct ProbeCache {
+    // None keeps the cache in memory only.
+    file: Option<PathBuf>,
+    // Loaded from `file` on first use.
+    cache: OnceLock<Mutex<CacheFile>>,
+    save_scheduled: AtomicBool,
+}
+
+impl ProbeCache {
+    pub(crate) fn new(file: Option<PathBuf>) -> Self {
+        ProbeCache {
+            file,
+            cache: OnceLock::new(),
+            save_scheduled: AtomicBool::new(false),
+        }
+    }
+
+    fn global() -> Arc<ProbeCache> {
+        PROBE_CACHE
+            .get_or_init(|| Arc::new(ProbeCache::new(cache_file_path().ok())))
+            .clone()
+    }
+
+    // A missing, unreadable or outdated cache file just means starting empty.
+    fn load(&self) -> CacheFile {
+        let Some(path) = &self.file else {
+            return CacheFile::default();
+        };
+        let Ok(content) = fs::read_to_string(path) else {
+            return CacheFile::default();
+        };
+        match serde_json::from_str::<CacheFile>(&content) {
+            Ok(cache) if cache.version == CACHE_VERSION => cache,
+            Ok(_) => CacheFile::default(),
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!(
+                    "[Probe Cache] Ignoring unreadable {}: {}",
+                    path.display(),
+                    _e
+                );
+                CacheFile::default()
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheFile> {
+        self.cache
+            .get_or_init(|| Mutex::new(self.load()))
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        let content = {
+            let mut cache = self.lock();
+            if cache.entries.len() > MAX_ENTRIES {
+                let mut by_age: Vec<(String, SystemTime)> = cache
+                    .entries
+                    .iter()
+                    .map(|(path, entry)| (path.clone(), entry.last_used))
+                    .collect();
+                by_age.sort_by_key(|(_, last_used)| *last_used);
+                let excess = cache.entries.len() - MAX_ENTRIES;
+                for (path, _) in by_age.into_iter().take(excess) {
+                    cache.entries.remove(&path);
+                }
+            }
+            cache.version = CACHE_VERSION;
+            serde_json::to_string(&*cache)
+                .map_err(|e| format!("Failed to serialize probe cache: {}", e))?
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        crate::recovery::replace_file(path, content.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn schedule_save(self: &Arc<Self>) {
+        if self.save_scheduled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let cache = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::time::sleep(SAVE_DELAY).await;
+            cache.save_scheduled.store(false, Ordering::SeqCst);
+            let result = tokio::task::spawn_blocking(move || cache.save()).await;
+            if let Ok(Err(_e)) = result {
+                #[cfg(debug_assertions)]
+                eprintln!("[Probe Cache] {}", _e);
+            }
+        });
+    }
+}
+
+// Identifies the current contents of a file on disk.
+struct FileKey {
+    path: String,
+    stamp: FileStamp,
+}
+
+impl FileKey {
+    // None if the file cannot be stat'ed, in which case the probe runs
+    // uncached and reports the real error.
+    fn of(path: &str) -> Option<FileKey> {
+        let canonical = fs::canonicalize(path).ok()?;
+        let metadata = fs::metadata(&canonical).ok()?;
+        Some(FileKey {
+            path: canonical.to_string_lossy().to_string(),
+            stamp: FileStamp {
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            },
+        })
+    }
+
+    fn lookup<R>(
+        &self,
+        cache: &ProbeCache,
+        read: impl FnOnce(&CacheEntry) -> Option<R>,
+    ) -> Option<R> {
+        let mut cache = cache.lock();
+        let entry = cache.entries.get_mut(&self.path)?;
+        if entry.stamp != self.stamp {
+            return None;
+        }
+        let value = read(entry)?;
+        entry.last_used = SystemTime::now();
+        Some(value)
+    }
+
+    fn store(&self, cache: &Arc<ProbeCache>, write: impl FnOnce(&mut CacheEntry)) {
+        {
+            let mut entries = cache.lock();
+            let entry = entries
+                .entries
+                .entry(self.path.clone())
+                .or_insert_with(|| CacheEntry::new(self.stamp));
+            if entry.stamp != self.stamp {
+                *entry = CacheEntry::new(self.stamp);
+            }
+            write(entry);
+            entry.last_used = SystemTime::now();
+        }
+        cache.schedule_save();
+    }
+}
+
+// Answers duration, stream, media info, chapter, integrity and loudness probes
+// from the on-disk cache when the file is unchanged, and records fresh results
+// otherwise. Everything else is passed straight through.
+pub(crate) struct CachedToolkit<T> {
+    inner: T,
+    cache: Arc<ProbeCache>,
+}
+
+impl<T> CachedToolkit<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Self::with_cache(inner, ProbeCache::global())
+    }
+
+    pub(crate) fn with_cache(inner: T, cache: Arc<ProbeCache>) -> Self {
+        CachedToolkit { inner, cache }
+    }
+}
+
+impl<T: MediaToolkit> MediaToolkit for CachedToolkit<T> {
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+
+    async fn probe_streams(
+        &self,
+        path: &str,
+        kind: StreamKind,
+    ) -> Result<Vec<ProbedStream>, FfmpegError> {
+        let Some(key) = FileKey::of(path) else {
+            return self.inner.probe_streams(path, kind).await;
+        };
+        if let Some(streams) = key.lookup(&self.cache, |entry| entry.streams.get(&kind).cloned()) {
+            return Ok(streams);
+        }
+
+        let streams = self.inner.probe_streams(path, kind).await?;
+        key.store(&self.cache, |entry| {
+            entry.streams.insert(kind, streams.clone());
+        });
+        Ok(streams)
+    }
+
+    async fn probe_media(&self, path: &str) -> Result<MediaInfo, FfmpegError> {
+        let Some(key) = FileKey::of(path) else {
+            return self.inner.probe_media(path).await;
+        };
+        if let Some(info) = key.lookup(&self.cache, |entry| entry.media_info.clone()) {
+            return Ok(info);
+        }
+
+        let info = self.inner.probe_media(path).await?;
+        key.store(&self.cache, |entry| entry.media_info = Some(info.clone()));
+        Ok(info)
+    }
+
+    async fn probe_chapters(&self, path: &str) -> Result<Vec<Chapter>, FfmpegError> {
+        let Some(key) = FileKey::of(path) else {
+            return self.inner.probe_chapters(path).await;
+        };
+        if let Some(chapters) = key.lookup(&self.cache, |entry| entry.chapters.clone()) {
+            return Ok(chapters);
+        }
+
+        let chapters = self.inner.probe_chapters(path).await?;
+        key.store(&self.cache, |entry| entry.chapters = Some(chapters.clone()));
+        Ok(chapters)
+    }
+
+    async fn probe_duration(&self, path: &str) -> Result<f64, FfmpegError> {
+        let Some(key) = FileKey::of(path) else {
+            return self.inner.probe_duration(path).await;
+        };
+        if let Some(duration) = key.lookup(&self.cache, |entry| entry.duration) {
+            return Ok(duration);
+        }
+
+        let duration = self.inner.probe_duration(path).await?;
+        key.store(&self.cache, |entry| entry.duration = Some(duration));
+        Ok(duration)
+    }
+
+    async fn extract_subtitle(&self, path: &str, stream_index: i64) -> Result<String, FfmpegError> {
+        self.inner.extract_subtitle(path, stream_index).await
+    }
+
+    async fn remux_audio_track(
+        &self,
+        path: &str,
+        audio_stream_index: i64,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.inner
+            .remux_audio_track(path, audio_stream_index, output)
+            .await
+    }
+
+    async fn extract_audio(&self, path: &str, output: &str) -> Result<(), FfmpegError> {
+        self.inner.extract_audio(path, output).await
+    }
+
+    async fn transcode(
+        &self,
+        input: &str,
+        output: &str,
+        target_format: &str,
+    ) -> Result<(), FfmpegError> {
+        self.inner.transcode(input, output, target_format).await
+    }
+
+    async fn remux(&self, input: &str, output: &str, container: &str) -> Result<(), FfmpegError> {
+        self.inner.remux(input, output, container).await
+    }
+
+    async fn transcode_audio(
+        &self,
+        input: &str,
+        output: &str,
+        container: &str,
+    ) -> Result<(), FfmpegError> {
+        self.inner.transcode_audio(input, output, container).await
+    }
+
+    async fn decode_check(
+        &self,
+        path: &str,
+        on_progress: impl Fn(f64) + Send + Sync,
+    ) -> Result<DecodeCheck, FfmpegError> {
+        let Some(key) = FileKey::of(path) else {
+            return self.inner.decode_check(path, on_progress).await;
+        };
+        if let Some(check) = key.lookup(&self.cache, |entry| entry.decode_check.clone()) {
+            on_progress(check.decoded_seconds);
+            return Ok(check);
+        }
+
+        let check = self.inner.decode_check(path, on_progress).await?;
+        key.store(&self.cache, |entry| {
+            entry.decode_check = Some(check.clone())
+        });
+        Ok(check)
+    }
+
+    async fn measure_loudness(&self, path: &str) -> Result<LoudnessMeasurement, FfmpegError> {
+        let Some(key) = FileKey::of(path) else {
+            return self.inner.measure_loudness(path).await;
+        };
+        if let Some(loudness) = key.lookup(&self.cache, |entry| entry.loudness) {
+            return Ok(loudness);
+        }
+
+        let loudness = self.inner.measure_loudness(path).await?;
+        key.store(&self.cache, |entry| entry.loudness = Some(loudness));
+        Ok(loudness)
+    }
+
+    async fn extract_cover_art(
+        &self,
+        path: &str,
+        stream_index: i64,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.inner
+            .extract_cover_art(path, stream_index, output)
+            .await
+    }
+
+    async fn extract_frame(
+        &self,
+        path: &str,
+        time: f64,
+        width: u32,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.inner.extract_frame(path, time, width, output).await
+    }
+
+    async fn render_sprite_sheet(
+        &self,
+        path: &str,
+        layout: SpriteLayout,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.inner.render_sprite_sheet(path, layout, output).await
+    }
+
+    async fn render_hover_preview(
+        &self,
+        path: &str,
+        starts: &[f64],
+        sample_secs: f64,
+        width: u32,
+        output: &str,
+    ) -> Result<(), FfmpegError> {
+        self.inner
+            .render_hover_preview(path, starts, sample_secs, width, output)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_toolkit::FakeToolkit;
+
+    // Each test gets its own cache that is never written to disk.
+    fn cached(toolkit: FakeToolkit) -> CachedToolkit<FakeToolkit> {
+        CachedToolkit::with_cache(toolkit, Arc::new(ProbeCache::new(None)))
+    }
+
+    // Paths that cannot be stat'ed are never cached, so every call reaches
+    // the wrapped toolkit.
+    #[tokio::test]
+    async fn unreadable_files_bypass_the_cache() {
+        let path = "/no/such/dir/film.mkv";
+        let chapters = vec![Chapter {
+            start: 0.0,
+            end: 60.0,
+            title: Some("Intro".to_string()),
+        }];
+        let check = DecodeCheck {
+            decoded_seconds: 60.0,
+            completed: true,
+            ..DecodeCheck::default()
+        };
+        let toolkit = cached(
+            FakeToolkit::new()
+                .with_media_info(path, MediaInfo::default())
+                .with_chapters(path, chapters)
+                .with_decode_check(path, check),
+        );
+
+        for _ in 0..2 {
+            assert_eq!(toolkit.probe_chapters(path).await.unwrap().len(), 1);
+            assert!(toolkit.probe_media(path).await.is_ok());
+            assert!(toolkit.decode_check(path, |_| {}).await.unwrap().completed);
+        }
+        assert_eq!(toolkit.inner.calls().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn media_info_is_probed_once_per_file_version() {
+        let dir = std::env::temp_dir().join(format!("probe-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("film.mkv");
+        fs::write(&file, b"first").unwrap();
+        let path = file.to_string_lossy().to_string();
+        let info = MediaInfo {
+            duration: Some(90.0),
+            ..MediaInfo::default()
+        };
+        let toolkit = cached(FakeToolkit::new().with_media_info(&path, info));
+
+        for _ in 0..2 {
+            let cached = toolkit.probe_media(&path).await.unwrap();
+            assert_eq!(cached.duration, Some(90.0));
+        }
+        assert_eq!(toolkit.inner.calls().len(), 1);
+
+        // A different size is a different file as far as the cache knows.
+        fs::write(&file, b"second version").unwrap();
+        toolkit.probe_media(&path).await.unwrap();
+        assert_eq!(toolkit.inner.calls().len(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn saved_entries_load_into_a_new_cache() {
+        let dir = std::env::temp_dir().join(format!("probe-cache-save-{}", std::process::id()));
+        let file = dir.join(CACHE_FILE_NAME);
+        let key = FileKey {
+            path: "/videos/film.mkv".to_string(),
+            stamp: FileStamp {
+                size: 1,
+                modified: None,
+            },
+        };
+
+        let cache = Arc::new(ProbeCache::new(Some(file.clone())));
+        cache.lock().entries.insert(key.path.clone(), {
+            let mut entry = CacheEntry::new(key.stamp);
+            entry.duration = Some(42.0);
+            entry
+        });
+        cache.save().unwrap();
+
+        let reloaded = ProbeCache::new(Some(file));
+        assert_eq!(reloaded.lock().version, CACHE_VERSION);
+        assert_eq!(key.lookup(&reloaded, |entry| entry.duration), Some(42.0));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn entries_without_newer_fields_still_load() {
+        let entry = serde_json::json!({
+            "stamp": {"size": 1, "modified": null},
+            "duration": 12.5,
+            "streams": {"Video": [{"index": 0, "codec_name": "h264", "language": null,
+                                   "title": null, "channels": null, "is_default": true}]},
+            "chapters": null,
+            "last_used": {"secs_since_epoch": 0, "nanos_since_epoch": 0}
+        });
+        let entry: CacheEntry = serde_json::from_value(entry).unwrap();
+        assert_eq!(entry.duration, Some(12.5));
+        assert!(!entry.streams[&StreamKind::Video][0].is_attached_pic);
+        assert!(entry.media_info.is_none());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::ffmpeg_error::{FfmpegError, FfmpegErrorKind};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use tokio::sync::oneshot;
+
+// Upper bound on ffmpeg/ffprobe processes running at once. Most of their cost
+// is disk reads, which get slower rather than faster in parallel on HDDs and
+// cloud-synced folders.
+const MAX_CONCURRENT_PROCESSES: usize = 3;
+
+// Transcodes and full decode checks run for minutes to hours, so they get a
+// pool of their own rather than holding a slot that probes are waiting on.
+// One at a time, since each of them reads the whole file.
+const MAX_LONG_RUNNING_PROCESSES: usize = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Priority {
+    // Work the user is waiting on, e.g. probing the file being opened.
+    Interactive,
+    // Work that can wait, e.g. durations for the library view.
+    Background,
+    // Jobs that process the whole file, e.g. transcodes. Runs in the separate
+    // long-running pool.
+    LongRunning,
+}
+
+type Waiter = oneshot::Sender<ProcessPermit>;
+
+// Waiters receive their permit through the channel. If a waiter gives up
+// before it is woken, the permit is dropped with the channel and the slot is
+// handed to the next one.
+struct SchedulerState {
+    running: usize,
+    interactive: VecDeque<Waiter>,
+    background: VecDeque<Waiter>,
+    long_running: usize,
+    long: VecDeque<Waiter>,
+}
+
+struct Scheduler {
+    state: Mutex<SchedulerState>,
+}
+
+// Shared by every ffmpeg/ffprobe spawn in the app.
+static SCHEDULER: Scheduler = Scheduler::new();
+
+impl SchedulerState {
+    fn queue(&mut self, priority: Priority) -> &mut VecDeque<Waiter> {
+        match priority {
+            Priority::Interactive => &mut self.interactive,
+            Priority::Background => &mut self.background,
+            Priority::LongRunning => &mut self.long,
+        }
+    }
+
+    // Interactive waiters always go first; background ones only get a slot
+    // when nothing interactive is queued. The long-running pool is plain FIFO.
+    fn next_waiter(&mut self, long: bool) -> Option<Waiter> {
+        if long {
+            return self.long.pop_front();
+        }
+        self.interactive
+            .pop_front()
+            .or_else(|| self.background.pop_front())
+    }
+}
+
+// Holds one of the process slots until dropped.
+pub(crate) struct ProcessPermit {
+    scheduler: &'static Scheduler,
+    // Which pool the slot belongs to.
+    long: bool,
+}
+
+impl Drop for ProcessPermit {
+    fn drop(&mut self) {
+        self.scheduler.release(self.long);
+    }
+}
+
+enum Slot {
+    Ready(ProcessPermit),
+    Queued(oneshot::Receiver<ProcessPermit>),
+}
+
+impl Scheduler {
+    const fn new() -> Self {
+        Scheduler {
+            state: Mutex::new(SchedulerState {
+                running: 0,
+                interactive: VecDeque::new(),
+                background: VecDeque::new(),
+                long_running: 0,
+                long: VecDeque::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SchedulerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Takes a free slot if nobody is queued ahead, otherwise joins the queue.
+    fn reserve(&'static self, priority: Priority) -> Slot {
+        let mut state = self.lock();
+        let (ahead, running, limit) = match priority {
+            Priority::Interactive => (
+                state.interactive.len(),
+                state.running,
+                MAX_CONCURRENT_PROCESSES,
+            ),
+            Priority::Background => (
+                state.interactive.len() + state.background.len(),
+                state.running,
+                MAX_CONCURRENT_PROCESSES,
+            ),
+            Priority::LongRunning => (
+                state.long.len(),
+                state.long_running,
+                MAX_LONG_RUNNING_PROCESSES,
+            ),
+        };
+        if ahead == 0 && running < limit {
+            let long = priority == Priority::LongRunning;
+            if long {
+                state.long_running += 1;
+            } else {
+                state.running += 1;
+            }
+            return Slot::Ready(ProcessPermit {
+                scheduler: self,
+                long,
+            });
+        }
+
+        #[cfg(debug_assertions)]
+        println!(
+            "[Scheduler] Queued {:?} process ({} running, {} waiting ahead)",
+            priority, running, ahead
+        );
+
+        let (sender, receiver) = oneshot::channel();
+        state.queue(priority).push_back(sender);
+        Slot::Queued(receiver)
+    }
+
+    fn release(&'static self, long: bool) {
+        let mut state = self.lock();
+        while let Some(waiter) = state.next_waiter(long) {
+            match waiter.send(ProcessPermit {
+                scheduler: self,
+                long,
+            }) {
+                // The slot moves to the waiter, so the running count is
+                // unchanged.
+                Ok(()) => return,
+                // The waiter was cancelled. Forget the returned permit rather
+                // than dropping it, which would re-enter this lock.
+                Err(permit) => std::mem::forget(permit),
+            }
+        }
+        let running = if long {
+            &mut state.long_running
+        } else {
+            &mut state.running
+        };
+        *running = running.saturating_sub(1);
+    }
+
+    // Waiters that were cancelled but not yet cleaned up are not counted.
+    fn status(&self) -> ProcessQueueStatus {
+        let state = self.lock();
+        let waiting =
+            |queue: &VecDeque<Waiter>| queue.iter().filter(|sender| !sender.is_closed()).count();
+        ProcessQueueStatus {
+            running: state.running,
+            limit: MAX_CONCURRENT_PROCESSES,
+            interactive_waiting: waiting(&state.interactive),
+            background_waiting: waiting(&state.background),
+            long_running: state.long_running,
+            long_running_limit: MAX_LONG_RUNNING_PROCESSES,
+            long_running_waiting: waiting(&state.long),
+        }
+    }
+}
+
+// The sender is only dropped after handing over a permit, so this should not
+// happen; it is reported rather than trusted.
+fn waiter_dropped() -> FfmpegError {
+    FfmpegError::new(
+        FfmpegErrorKind::Other,
+        "The process scheduler dropped a waiting job",
+    )
+}
+
+// Waits for a free process slot. Hold the returned permit for as long as the
+// child process runs.
+pub(crate) async fn acquire(priority: Priority) -> Result<ProcessPermit, FfmpegError> {
+    match SCHEDULER.reserve(priority) {
+        Slot::Ready(permit) => Ok(permit),
+        Slot::Queued(receiver) => receiver.await.map_err(|_| waiter_dropped()),
+    }
+}
+
+// `acquire` for synchronous code such as path resolution. On a runtime worker
+// thread the wait happens inside block_in_place, so the tasks holding the
+// slots keep running. Not usable from a current-thread runtime.
+pub(crate) fn acquire_blocking(priority: Priority) -> Result<ProcessPermit, FfmpegError> {
+    match SCHEDULER.reserve(priority) {
+        Slot::Ready(permit) => Ok(permit),
+        Slot::Queued(receiver) => {
+            tokio::task::block_in_place(|| receiver.blocking_recv()).map_err(|_| waiter_dropped())
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct ProcessQueueStatus {
+    running: usize,
+    limit: usize,
+    interactive_waiting: usize,
+    background_waiting: usize,
+    long_running: usize,
+    long_running_limit: usize,
+    long_running_waiting: usize,
+}
+
+// Queue depth for the UI and diagnostics.
+#[tauri::command]
+pub(crate) fn get_process_queue_status() -> ProcessQueueStatus {
+    SCHEDULER.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A scheduler of its own, so permits taken by other tests running in
+    // parallel do not affect the counts.
+    fn scheduler() -> &'static Scheduler {
+        Box::leak(Box::new(Scheduler::new()))
+    }
+
+    fn ready(scheduler: &'static Scheduler, priority: Priority) -> ProcessPermit {
+        match scheduler.reserve(priority) {
+            Slot::Ready(permit) => permit,
+            Slot::Queued(_) => panic!("{:?} job was queued", priority),
+        }
+    }
+
+    #[test]
+    fn long_running_jobs_have_their_own_pool() {
+        let scheduler = scheduler();
+        let transcode = ready(scheduler, Priority::LongRunning);
+        let probes: Vec<_> = (0..MAX_CONCURRENT_PROCESSES)
+            .map(|_| ready(scheduler, Priority::Interactive))
+            .collect();
+
+        let Slot::Queued(mut second) = scheduler.reserve(Priority::LongRunning) else {
+            panic!("a second long-running job got a slot");
+        };
+        assert!(second.try_recv().is_err());
+
+        drop(probes);
+        assert!(second.try_recv().is_err());
+        drop(transcode);
+        let handed_over = second.try_recv().unwrap();
+        assert!(handed_over.long);
+        assert_eq!(scheduler.status().long_running, 1);
+
+        drop(handed_over);
+        let status = scheduler.status();
+        assert_eq!((status.running, status.long_running), (0, 0));
+    }
+
+    #[test]
+    fn interactive_waiters_go_before_background_ones() {
+        let scheduler = scheduler();
+        let running: Vec<_> = (0..MAX_CONCURRENT_PROCESSES)
+            .map(|_| ready(scheduler, Priority::Background))
+            .collect();
+
+        let Slot::Queued(mut background) = scheduler.reserve(Priority::Background) else {
+            panic!("background job got a slot while the pool was full");
+        };
+        let Slot::Queued(mut interactive) = scheduler.reserve(Priority::Interactive) else {
+            panic!("interactive job got a slot while the pool was full");
+        };
+        assert_eq!(scheduler.status().interactive_waiting, 1);
+
+        let mut running = running.into_iter();
+        drop(running.next());
+        let _interactive = interactive.try_recv().unwrap();
+        assert!(background.try_recv().is_err());
+
+        drop(running.next());
+        let _background = background.try_recv().unwrap();
+        assert_eq!(scheduler.status().running, MAX_CONCURRENT_PROCESSES);
+    }
+
+    #[test]
+    fn cancelled_waiters_are_skipped() {
+        let scheduler = scheduler();
+        let running: Vec<_> = (0..MAX_CONCURRENT_PROCESSES)
+            .map(|_| ready(scheduler, Priority::Interactive))
+            .collect();
+        let Slot::Queued(cancelled) = scheduler.reserve(Priority::Interactive) else {
+            panic!("job got a slot while the pool was full");
+        };
+        drop(cancelled);
+
+        drop(running);
+        assert_eq!(scheduler.status().running, 0);
+    }
+}
@@ -0,0 +1,285 @@
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use tauri::{AppHandle, Emitter};
+
+// Number of rotating backups kept next to each data file (`<file>.bak.1` is
+// the newest).
+const MAX_BACKUPS: usize = 3;
+
+// Set once the frontend has called `frontend_ready`. Recoveries that happen
+// before then (e.g. while reading config during startup) are queued.
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+static PENDING_NOTICES: Mutex<Vec<RecoveryNotice>> = Mutex::new(Vec::new());
+
+// Makes temp file names unique so concurrent writers never share one.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Clone, Debug)]
+struct RecoveryNotice {
+    file: String,
+    error: String,
+    // Where the unreadable file was moved so the user can inspect it.
+    quarantined_to: Option<String>,
+    // Backup that replaced it, or None if nothing usable was found and
+    // defaults were used instead.
+    restored_from: Option<String>,
+}
+
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".bak.{}", index));
+    path.with_file_name(name)
+}
+
+// `<file>.corrupt-<unix millis>`, with a counter appended if that name is
+// already taken, so two failures in quick succession never overwrite each
+// other's evidence.
+fn quarantine_path(path: &Path) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let base = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut candidate = path.with_file_name(format!("{}.corrupt-{}", base, millis));
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = path.with_file_name(format!("{}.corrupt-{}-{}", base, millis, counter));
+        counter += 1;
+    }
+    candidate
+}
+
+fn report(notice: RecoveryNotice) {
+    #[cfg(debug_assertions)]
+    eprintln!(
+        "[Recovery] {} was unreadable ({}); restored from {:?}",
+        notice.file, notice.error, notice.restored_from
+    );
+
+    match APP_HANDLE.get() {
+        Some(app_handle) => {
+            let _ = app_handle.emit("config-recovered", notice);
+        }
+        None => PENDING_NOTICES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(notice),
+    }
+}
+
+// Called from `frontend_ready`: delivers any recoveries that happened before
+// the UI was listening and emits future ones immediately.
+pub(crate) fn frontend_ready(app_handle: &AppHandle) {
+    let _ = APP_HANDLE.set(app_handle.clone());
+    let pending: Vec<RecoveryNotice> = PENDING_NOTICES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .drain(..)
+        .collect();
+    for notice in pending {
+        let _ = app_handle.emit("config-recovered", notice);
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// Follows `path` through any chain of symlinks to the file they point at,
+// which may not exist yet. Dotfile managers commonly link config files into
+// place, and renaming over the link would silently replace it with a copy.
+pub(crate) fn resolve_symlink(path: &Path) -> PathBuf {
+    let mut resolved = path.to_path_buf();
+    // Bounded so a symlink loop cannot spin forever.
+    for _ in 0..32 {
+        let Ok(target) = fs::read_link(&resolved) else {
+            break;
+        };
+        resolved = match resolved.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+    }
+    resolved
+}
+
+// Replaces `path` with `content` via a synced temp file and rename, so a crash
+// mid-write never leaves a truncated file behind. A symlinked `path` keeps its
+// link and the file it points at is replaced instead.
+pub(crate) fn replace_file(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let path = &resolve_symlink(path);
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_file = path.with_file_name(temp_name);
+
+    let result = fs::File::create(&temp_file)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_file, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_file);
+    }
+    result
+}
+
+// Shifts `<file>.bak.N` up by one and stores a fresh copy of `path` as
+// `<file>.bak.1`. Failures are logged but never fail the surrounding write.
+fn rotate_backups(path: &Path) {
+    for index in (1..MAX_BACKUPS).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            let _ = fs::rename(&from, backup_path(path, index + 1));
+        }
+    }
+    if let Err(_err) = fs::copy(path, backup_path(path, 1)) {
+        #[cfg(debug_assertions)]
+        eprintln!("[Recovery] Failed to back up {}: {}", path.display(), _err);
+    }
+}
+
+// Atomically replaces `path` with `content` (synced temp file + rename) and
+// then rotates its backups, so every successful write leaves a known-good copy.
+pub(crate) fn write_with_backups(path: &Path, content: &str) -> Result<(), String> {
+    replace_file(path, content.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", display_name(path), e))?;
+    rotate_backups(path);
+    Ok(())
+}
+
+// Reads and parses `path`. Returns Ok(None) when the file does not exist.
+//
+// If the file exists but does not parse, it is moved aside as
+// `<file>.corrupt-<timestamp>` and the newest backup that parses is restored
+// in its place. When no backup is usable the caller gets Ok(None) and falls
+// back to defaults. Either way a `config-recovered` event tells the UI.
+//
+// Recovery writes, so callers must hold whatever lock keeps other writers
+// (and other processes) away from `path`, not just one for reading.
+pub(crate) fn read_with_recovery<T, F>(path: &Path, parse: F) -> Result<Option<T>, String>
+where
+    F: Fn(&str) -> Result<T, String>,
+{
+    let file_name = display_name(path);
+
+    let error = match fs::read_to_string(path) {
+        Ok(content) => match parse(&content) {
+            Ok(value) => return Ok(Some(value)),
+            Err(e) => e,
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", file_name, e)),
+    };
+
+    // Move the broken file itself aside, not a symlink pointing at it.
+    let target = resolve_symlink(path);
+    let quarantine = quarantine_path(&target);
+    let quarantined_to = match fs::rename(&target, &quarantine) {
+        Ok(()) => Some(quarantine.to_string_lossy().to_string()),
+        Err(_) => None,
+    };
+
+    for index in 1..=MAX_BACKUPS {
+        let backup = backup_path(path, index);
+        let Ok(content) = fs::read_to_string(&backup) else {
+            continue;
+        };
+        let Ok(value) = parse(&content) else {
+            continue;
+        };
+
+        // Put the good copy back in place so other readers (and the next
+        // launch) see it too.
+        let _ = replace_file(path, content.as_bytes());
+
+        report(RecoveryNotice {
+            file: file_name,
+            error,
+            quarantined_to,
+            restored_from: Some(backup.to_string_lossy().to_string()),
+        });
+        return Ok(Some(value));
+    }
+
+    report(RecoveryNotice {
+        file: file_name,
+        error,
+        quarantined_to,
+        restored_from: None,
+    });
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("glucose-recovery-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replace_file_keeps_symlink() {
+        let dir = scratch_dir("symlink");
+        let target = dir.join("dotfiles-config.json");
+        let link = dir.join("config.json");
+        fs::write(&target, "{}").unwrap();
+        std::os::unix::fs::symlink("dotfiles-config.json", &link).unwrap();
+
+        replace_file(&link, b"{\"a\":1}").unwrap();
+
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "{\"a\":1}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn quarantine_names_never_collide() {
+        let dir = scratch_dir("quarantine");
+        let file = dir.join("watch_progress.json");
+        let first = quarantine_path(&file);
+        fs::write(&first, "").unwrap();
+        let second = quarantine_path(&file);
+
+        assert_ne!(first, second);
+        assert!(!second.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupt_file_is_restored_from_newest_good_backup() {
+        let dir = scratch_dir("restore");
+        let file = dir.join("data.json");
+        let parse = |content: &str| serde_json::from_str::<u32>(content).map_err(|e| e.to_string());
+        fs::write(&file, "{ broken").unwrap();
+        fs::write(backup_path(&file, 1), "also broken").unwrap();
+        fs::write(backup_path(&file, 2), "7").unwrap();
+
+        assert_eq!(read_with_recovery(&file, parse).unwrap(), Some(7));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "7");
+        let quarantined = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().contains(".corrupt-"))
+            .count();
+        assert_eq!(quarantined, 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
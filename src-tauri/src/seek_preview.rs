@@ -0,0 +1,201 @@
+use crate::media_cache;
+use crate::media_toolkit::{CliToolkit, MediaToolkit};
+use crate::probe_cache::CachedToolkit;
+use serde::Serialize;
+use std::fmt::Write;
+use std::fs;
+
+// Subdirectory of the cache directory holding sprite sheets and their tracks.
+const SEEK_PREVIEW_DIR_NAME: &str = "seek_previews";
+
+// A full sheet at the limits below is about 400 KB.
+const MAX_SEEK_PREVIEW_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+// Frames are letterboxed into fixed-size tiles so cue coordinates can be
+// written without knowing the video's dimensions.
+const TILE_WIDTH: u32 = 160;
+const TILE_HEIGHT: u32 = 90;
+const COLUMNS: u32 = 10;
+
+// One frame every this many seconds, stretched for long files so the sheet
+// never holds more than MAX_TILES frames.
+const BASE_INTERVAL_SECS: f64 = 10.0;
+const MAX_TILES: u32 = 200;
+
+// How frames are laid out in a sprite sheet, row by row from the top left.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SpriteLayout {
+    // Seconds between frames.
+    pub(crate) interval: f64,
+    pub(crate) tile_width: u32,
+    pub(crate) tile_height: u32,
+    pub(crate) columns: u32,
+    pub(crate) rows: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SeekPreview {
+    sprite_path: String,
+    // WebVTT track whose cues point at regions of the sprite sheet through
+    // `#xywh=` fragments, relative to the sheet's file name.
+    vtt_path: String,
+    interval: f64,
+}
+
+fn layout_for(duration: f64) -> (SpriteLayout, u32) {
+    let interval = BASE_INTERVAL_SECS.max((duration / MAX_TILES as f64).ceil());
+    let tiles = ((duration / interval).ceil() as u32).clamp(1, MAX_TILES);
+    let columns = COLUMNS.min(tiles);
+    let layout = SpriteLayout {
+        interval,
+        tile_width: TILE_WIDTH,
+        tile_height: TILE_HEIGHT,
+        columns,
+        rows: tiles.div_ceil(columns),
+    };
+    (layout, tiles)
+}
+
+fn vtt_timestamp(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+fn build_vtt(layout: SpriteLayout, tiles: u32, duration: f64, sprite_name: &str) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+    for tile in 0..tiles {
+        let start = tile as f64 * layout.interval;
+        let end = ((tile + 1) as f64 * layout.interval).min(duration);
+        let x = tile % layout.columns * layout.tile_width;
+        let y = tile / layout.columns * layout.tile_height;
+        let _ = write!(
+            vtt,
+            "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+            vtt_timestamp(start),
+            vtt_timestamp(end),
+            sprite_name,
+            x,
+            y,
+            layout.tile_width,
+            layout.tile_height
+        );
+    }
+    vtt
+}
+
+pub(crate) async fn seek_preview<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+) -> Result<SeekPreview, String> {
+    let duration = toolkit.probe_duration(video_path).await?.max(0.0);
+    if duration == 0.0 {
+        return Err(format!("{} has no duration to preview", video_path));
+    }
+    let (layout, tiles) = layout_for(duration);
+
+    let digest = media_cache::file_digest(
+        video_path,
+        &format!("{}x{}@{}", TILE_WIDTH, TILE_HEIGHT, layout.interval),
+    )?;
+    let dir = media_cache::dir(SEEK_PREVIEW_DIR_NAME)?;
+    let sprite = dir.join(format!("{}.jpg", digest));
+    let vtt = dir.join(format!("{}.vtt", digest));
+
+    // The two files are cached and evicted independently. Both are derived
+    // from the same layout, so whichever one is missing is simply rebuilt.
+    media_cache::get_or_create(
+        &sprite,
+        MAX_SEEK_PREVIEW_CACHE_BYTES,
+        |partial| async move {
+            toolkit
+                .render_sprite_sheet(video_path, layout, &partial)
+                .await
+        },
+    )
+    .await?;
+
+    let sprite_name = sprite
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let track = build_vtt(layout, tiles, duration, &sprite_name);
+    media_cache::get_or_create(&vtt, MAX_SEEK_PREVIEW_CACHE_BYTES, |partial| async move {
+        fs::write(&partial, track).map_err(|e| format!("Failed to write thumbnail track: {}", e))
+    })
+    .await?;
+
+    #[cfg(debug_assertions)]
+    println!(
+        "[Seek Preview] {}: {} tiles every {}s",
+        video_path, tiles, layout.interval
+    );
+
+    Ok(SeekPreview {
+        sprite_path: sprite.to_string_lossy().to_string(),
+        vtt_path: vtt.to_string_lossy().to_string(),
+        interval: layout.interval,
+    })
+}
+
+// Sprite sheet and WebVTT thumbnail track for hovering the progress bar.
+// Rendering runs at background priority and can take a while on the first
+// request for a file; the result is cached until the file changes, so the
+// command simply resolves once both files are ready.
+#[tauri::command]
+pub(crate) async fn get_seek_preview(video_path: String) -> Result<SeekPreview, String> {
+    seek_preview(&CachedToolkit::new(CliToolkit), &video_path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_files_get_one_frame_per_interval() {
+        let (layout, tiles) = layout_for(95.0);
+        assert_eq!(layout.interval, BASE_INTERVAL_SECS);
+        assert_eq!(tiles, 10);
+        assert_eq!((layout.columns, layout.rows), (10, 1));
+
+        let (layout, tiles) = layout_for(3.0);
+        assert_eq!((tiles, layout.columns, layout.rows), (1, 1, 1));
+    }
+
+    #[test]
+    fn long_files_are_capped_at_max_tiles() {
+        let (layout, tiles) = layout_for(3.0 * 3600.0);
+        assert_eq!(layout.interval, 54.0);
+        assert_eq!(tiles, MAX_TILES);
+        assert_eq!(layout.rows, MAX_TILES / COLUMNS);
+    }
+
+    #[test]
+    fn cues_point_at_tiles_row_by_row() {
+        let (layout, tiles) = layout_for(115.0);
+        let vtt = build_vtt(layout, tiles, 115.0, "sheet.jpg");
+        let cues: Vec<&str> = vtt.split("\n\n").skip(1).collect();
+
+        assert!(vtt.starts_with("WEBVTT\n"));
+        assert_eq!(cues.len(), 12);
+        assert_eq!(
+            cues[0],
+            "00:00:00.000 --> 00:00:10.000\nsheet.jpg#xywh=0,0,160,90"
+        );
+        assert_eq!(
+            cues[11].trim_end(),
+            "00:01:50.000 --> 00:01:55.000\nsheet.jpg#xywh=160,90,160,90"
+        );
+    }
+
+    #[test]
+    fn timestamps_roll_over_into_hours() {
+        assert_eq!(vtt_timestamp(3723.4567), "01:02:03.457");
+    }
+}
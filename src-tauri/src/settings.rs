@@ -0,0 +1,551 @@
+use crate::folder_settings::MediaPreferences;
+use crate::pip_window::PipWindowLayout;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+// Current schema version of config.json. Bump this and append a step to
+// MIGRATIONS whenever a stored field is renamed, retyped or removed.
+pub(crate) const SETTINGS_VERSION: u32 = 1;
+
+// Forward migrations over the raw JSON document. Entry `i` upgrades a config
+// from version `i` to `i + 1`; files written before versioning are version 0.
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[migrate_v0_to_v1];
+
+// Editors and dotfile managers often write config.json in several steps
+// (truncate + write, or temp file + rename). Wait this long after the last
+// filesystem event before re-reading so we only see the finished file.
+const CONFIG_RELOAD_DEBOUNCE_MS: u64 = 250;
+
+// Last config contents this process read or wrote, as top-level JSON keys.
+// External edits are diffed against it, and our own saves update it so they
+// do not echo back as `config-changed` events.
+static LAST_SEEN_CONFIG: Mutex<Option<Map<String, Value>>> = Mutex::new(None);
+
+// Every known field is read leniently: a value of the wrong type (usually a
+// hand edit) falls back to the field's default instead of failing the whole
+// document, so one typo never costs the user their other settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    #[serde(deserialize_with = "lenient")]
+    pub(crate) version: u32,
+    // None means the user never customised the library folders, so the
+    // platform defaults are used.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "lenient_string_list"
+    )]
+    pub(crate) gallery_paths: Option<Vec<String>>,
+    #[serde(deserialize_with = "lenient")]
+    pub(crate) setup_completed: bool,
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) ffmpeg_custom_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) ffprobe_custom_path: Option<String>,
+    // Where `install_managed_ffmpeg` downloads from: an http(s) URL, or a
+    // file:// URL / absolute path for an offline mirror.
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) managed_ffmpeg_base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub(crate) pip_window: Option<PipWindowLayout>,
+    // Global playback defaults; per-folder `.glucose.json` files override them.
+    #[serde(
+        skip_serializing_if = "MediaPreferences::is_empty",
+        deserialize_with = "lenient"
+    )]
+    pub(crate) media: MediaPreferences,
+    // Keys this build does not know about (written by a newer version or by
+    // hand) are carried through untouched so a save never drops them.
+    #[serde(flatten)]
+    pub(crate) extra: Map<String, Value>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            gallery_paths: None,
+            setup_completed: false,
+            ffmpeg_custom_path: None,
+            ffprobe_custom_path: None,
+            managed_ffmpeg_base_url: None,
+            pip_window: None,
+            media: MediaPreferences::default(),
+            extra: Map::new(),
+        }
+    }
+}
+
+// Deserializes a field, falling back to its default when the stored value has
+// the wrong shape.
+pub(crate) fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Default,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).unwrap_or_else(|_err| {
+        #[cfg(debug_assertions)]
+        eprintln!("[Config] Ignoring invalid value: {}", _err);
+        T::default()
+    }))
+}
+
+// Library folders were always read this way: non-string entries are skipped,
+// and anything other than a list means "use the defaults".
+fn lenient_string_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(items) => Some(
+            items
+                .into_iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .collect(),
+        ),
+        _ => None,
+    })
+}
+
+// v0 files were assembled key-by-key by each command. An empty
+// ffmpeg_custom_path string was used to mean "unset", which is now None.
+fn migrate_v0_to_v1(config: &mut Map<String, Value>) {
+    if config
+        .get("ffmpeg_custom_path")
+        .and_then(|v| v.as_str())
+        .is_some_and(|s| s.is_empty())
+    {
+        config.remove("ffmpeg_custom_path");
+    }
+}
+
+pub(crate) fn config_path() -> Result<PathBuf, String> {
+    Ok(crate::paths::config_dir()?.join("config.json"))
+}
+
+fn migrate(mut config: Map<String, Value>) -> Map<String, Value> {
+    let mut version = config.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+
+    while version < MIGRATIONS.len() {
+        MIGRATIONS[version](&mut config);
+        version += 1;
+    }
+
+    if version <= SETTINGS_VERSION as usize {
+        config.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    }
+    config
+}
+
+fn parse_settings(content: &str) -> Result<Settings, String> {
+    let config: Value =
+        serde_json::from_str(content).map_err(|e| format!("Failed to parse config: {}", e))?;
+    settings_from_value(config)
+}
+
+// Migrates a config document, whether read from disk or taken from the
+// settings section of a backup bundle. Only a root that is not an object is an
+// error; bad field values are dropped field by field.
+pub(crate) fn settings_from_value(config: Value) -> Result<Settings, String> {
+    let Value::Object(config) = config else {
+        return Err("Config root must be a JSON object".to_string());
+    };
+    serde_json::from_value(Value::Object(migrate(config)))
+        .map_err(|e| format!("Failed to parse config: {}", e))
+}
+
+// Reads and migrates config.json without taking CONFIG_MUTEX. Callers must
+// already hold the lock. A corrupt file is replaced by its newest good backup
+// (or defaults) rather than failing every command that needs settings.
+fn read_settings(config_file: &Path) -> Result<Settings, String> {
+    Ok(crate::recovery::read_with_recovery(config_file, parse_settings)?.unwrap_or_default())
+}
+
+// Writes config.json atomically and rotates its backups. Callers must already
+// hold CONFIG_MUTEX.
+fn write_settings(config_file: &Path, settings: &Settings) -> Result<(), String> {
+    let config_dir = config_file
+        .parent()
+        .ok_or_else(|| "Could not get config directory".to_string())?;
+    fs::create_dir_all(config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+
+    let mut settings = settings.clone();
+    // Never stamp a file from a newer build down to our version.
+    settings.version = settings.version.max(SETTINGS_VERSION);
+
+    let content = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    crate::recovery::write_with_backups(config_file, &content)?;
+
+    remember_config(&settings);
+
+    Ok(())
+}
+
+fn config_snapshot(settings: &Settings) -> Map<String, Value> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+// Records `settings` as the latest known config and returns the previous one.
+fn remember_config(settings: &Settings) -> Option<Map<String, Value>> {
+    LAST_SEEN_CONFIG
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(config_snapshot(settings))
+}
+
+pub(crate) fn load_settings() -> Result<Settings, String> {
+    let config_file = config_path()?;
+    let _guard = crate::CONFIG_MUTEX
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    read_settings(&config_file)
+}
+
+// Runs a read-modify-write cycle on config.json under CONFIG_MUTEX and returns
+// the settings as saved. `mutate` must not call back into this module.
+pub(crate) fn modify_settings<F>(mutate: F) -> Result<Settings, String>
+where
+    F: FnOnce(&mut Settings) -> Result<(), String>,
+{
+    let config_file = config_path()?;
+    let (settings, tool_paths_changed) = {
+        let _guard = crate::CONFIG_MUTEX
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let mut settings = read_settings(&config_file)?;
+        let previous_ffmpeg_path = settings.ffmpeg_custom_path.clone();
+        let previous_ffprobe_path = settings.ffprobe_custom_path.clone();
+        mutate(&mut settings)?;
+        write_settings(&config_file, &settings)?;
+
+        let changed = settings.ffmpeg_custom_path != previous_ffmpeg_path
+            || settings.ffprobe_custom_path != previous_ffprobe_path;
+        (settings, changed)
+    };
+
+    // Outside CONFIG_MUTEX: resolving ffmpeg reads the config, so the cache
+    // lock must never be taken while this one is held.
+    if tool_paths_changed {
+        crate::ffmpeg::invalidate_ffmpeg_cache();
+    }
+
+    Ok(settings)
+}
+
+#[tauri::command]
+pub(crate) fn get_settings() -> Result<Settings, String> {
+    load_settings()
+}
+
+#[tauri::command]
+pub(crate) fn update_settings(settings: Settings) -> Result<Settings, String> {
+    let mut incoming = settings;
+    incoming.ffmpeg_custom_path = incoming.ffmpeg_custom_path.filter(|p| !p.is_empty());
+    incoming.ffprobe_custom_path = incoming.ffprobe_custom_path.filter(|p| !p.is_empty());
+    incoming.managed_ffmpeg_base_url = incoming
+        .managed_ffmpeg_base_url
+        .filter(|u| !u.trim().is_empty());
+
+    modify_settings(|current| {
+        // Only re-validate the FFmpeg/FFprobe paths when they actually change,
+        // so an unrelated edit is not blocked by a binary that has since moved.
+        if incoming.ffmpeg_custom_path != current.ffmpeg_custom_path {
+            if let Some(path) = &incoming.ffmpeg_custom_path {
+                crate::ffmpeg::validate_ffmpeg_path(path)?;
+            }
+        }
+        if incoming.ffprobe_custom_path != current.ffprobe_custom_path {
+            if let Some(path) = &incoming.ffprobe_custom_path {
+                crate::ffmpeg::validate_ffprobe_path(path)?;
+            }
+        }
+        *current = incoming;
+        Ok(())
+    })
+}
+
+#[derive(Clone, Serialize)]
+struct ConfigFieldChange {
+    key: String,
+    old: Option<Value>,
+    new: Option<Value>,
+}
+
+#[derive(Clone, Serialize)]
+struct ConfigChanged {
+    changes: Vec<ConfigFieldChange>,
+    settings: Settings,
+}
+
+fn diff_config(
+    previous: &Map<String, Value>,
+    current: &Map<String, Value>,
+) -> Vec<ConfigFieldChange> {
+    let mut keys: Vec<&String> = previous.keys().chain(current.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter(|key| key.as_str() != "version")
+        .filter(|key| previous.get(*key) != current.get(*key))
+        .map(|key| ConfigFieldChange {
+            key: key.clone(),
+            old: previous.get(key).cloned(),
+            new: current.get(key).cloned(),
+        })
+        .collect()
+}
+
+#[derive(Clone, Serialize)]
+struct ConfigInvalid {
+    error: String,
+}
+
+// Re-reads config.json after an external edit and tells the frontend what
+// changed. This bypasses recovery: a file that does not parse is most likely
+// still being edited or saved, so the settings already in use are kept, the
+// file is left alone and `config-invalid` reports the problem instead.
+fn reload_config(app_handle: &AppHandle, config_file: &Path) {
+    let settings = {
+        let _guard = crate::CONFIG_MUTEX
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let parsed = match fs::read_to_string(config_file) {
+            Ok(content) => parse_settings(&content),
+            // Mid-save or deleted; the next event picks up whatever replaces it.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => Err(format!("Failed to read config: {}", e)),
+        };
+        match parsed {
+            Ok(settings) => settings,
+            Err(error) => {
+                #[cfg(debug_assertions)]
+                eprintln!("[Config] Ignoring invalid external edit: {}", error);
+                let _ = app_handle.emit("config-invalid", ConfigInvalid { error });
+                return;
+            }
+        }
+    };
+
+    let previous = remember_config(&settings).unwrap_or_default();
+    let changes = diff_config(&previous, &config_snapshot(&settings));
+    if changes.is_empty() {
+        return;
+    }
+
+    #[cfg(debug_assertions)]
+    println!(
+        "[Config] Reloaded external edit, changed: {:?}",
+        changes.iter().map(|c| c.key.as_str()).collect::<Vec<_>>()
+    );
+
+    if changes
+        .iter()
+        .any(|c| c.key == "ffmpeg_custom_path" || c.key == "ffprobe_custom_path")
+    {
+        crate::ffmpeg::invalidate_ffmpeg_cache();
+    }
+
+    if changes.iter().any(|c| c.key == "pip_window") {
+        if let Err(_err) = crate::pip_window::apply_saved_pip_layout(app_handle) {
+            #[cfg(debug_assertions)]
+            eprintln!("[Config] Failed to apply reloaded PiP layout: {}", _err);
+        }
+    }
+
+    let _ = app_handle.emit("config-changed", ConfigChanged { changes, settings });
+}
+
+// Watches the config directory so edits made outside the app (dotfile
+// tooling, a text editor) take effect in a running instance. The directory
+// is watched rather than the file because atomic saves replace the inode.
+// When config.json is a symlink, the directory of the file it points at is
+// watched as well, since edits there never touch the link itself.
+pub(crate) fn start_config_watcher(app_handle: AppHandle) {
+    let Ok(config_file) = config_path() else {
+        return;
+    };
+    let Some(config_dir) = config_file.parent().map(Path::to_path_buf) else {
+        return;
+    };
+    if fs::create_dir_all(&config_dir).is_err() {
+        return;
+    }
+    let target_file = crate::recovery::resolve_symlink(&config_file);
+    let target_dir = target_file
+        .parent()
+        .filter(|dir| *dir != config_dir)
+        .map(Path::to_path_buf);
+    if let Ok(settings) = load_settings() {
+        remember_config(&settings);
+    }
+
+    thread::spawn(move || {
+        let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(w) => w,
+            Err(_err) => {
+                #[cfg(debug_assertions)]
+                eprintln!("[Config] Failed to create config watcher: {}", _err);
+                return;
+            }
+        };
+        if let Err(_err) = watcher.watch(&config_dir, RecursiveMode::NonRecursive) {
+            #[cfg(debug_assertions)]
+            eprintln!(
+                "[Config] Failed to watch {}: {}",
+                config_dir.display(),
+                _err
+            );
+            return;
+        }
+        if let Some(target_dir) = &target_dir {
+            if let Err(_err) = watcher.watch(target_dir, RecursiveMode::NonRecursive) {
+                #[cfg(debug_assertions)]
+                eprintln!(
+                    "[Config] Failed to watch {}: {}",
+                    target_dir.display(),
+                    _err
+                );
+            }
+        }
+
+        while let Ok(event) = rx.recv() {
+            let touches_config = match &event {
+                Ok(event) => {
+                    !matches!(event.kind, EventKind::Access(_))
+                        && event.paths.iter().any(|p| {
+                            p.file_name() == config_file.file_name()
+                                || p.file_name() == target_file.file_name()
+                        })
+                }
+                Err(_) => false,
+            };
+            if !touches_config {
+                continue;
+            }
+
+            while rx
+                .recv_timeout(Duration::from_millis(CONFIG_RELOAD_DEBOUNCE_MS))
+                .is_ok()
+            {}
+            reload_config(&app_handle, &config_file);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrates_unversioned_config() {
+        let config = migrate(
+            json!({ "ffmpeg_custom_path": "", "setup_completed": true })
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+        assert_eq!(config.get("version"), Some(&json!(SETTINGS_VERSION)));
+        assert!(!config.contains_key("ffmpeg_custom_path"));
+        assert_eq!(config.get("setup_completed"), Some(&json!(true)));
+    }
+
+    #[test]
+    fn keeps_version_written_by_newer_build() {
+        let config = migrate(json!({ "version": 99 }).as_object().unwrap().clone());
+        assert_eq!(config.get("version"), Some(&json!(99)));
+    }
+
+    #[test]
+    fn bad_field_does_not_discard_the_rest() {
+        let settings = parse_settings(
+            r#"{
+                "version": 1,
+                "setup_completed": true,
+                "gallery_paths": ["/videos", 42, "/films"],
+                "pip_window": "not a layout",
+                "ffmpeg_custom_path": 7,
+                "media": { "audio_language": "jpn", "end_behavior": "sideways" }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(settings.setup_completed);
+        assert_eq!(
+            settings.gallery_paths,
+            Some(vec!["/videos".to_string(), "/films".to_string()])
+        );
+        assert!(settings.pip_window.is_none());
+        assert!(settings.ffmpeg_custom_path.is_none());
+        assert_eq!(settings.media.audio_language.as_deref(), Some("jpn"));
+        assert!(settings.media.end_behavior.is_none());
+    }
+
+    #[test]
+    fn malformed_media_section_is_dropped() {
+        let settings = parse_settings(r#"{ "media": "jpn", "setup_completed": true }"#).unwrap();
+        assert!(settings.media.is_empty());
+        assert!(settings.setup_completed);
+    }
+
+    #[test]
+    fn non_list_gallery_paths_means_defaults() {
+        let settings = parse_settings(r#"{ "gallery_paths": "/videos" }"#).unwrap();
+        assert!(settings.gallery_paths.is_none());
+    }
+
+    #[test]
+    fn unknown_keys_survive_a_round_trip() {
+        let settings = parse_settings(r#"{ "future_option": { "a": 1 } }"#).unwrap();
+        let saved = serde_json::to_value(&settings).unwrap();
+        assert_eq!(saved["future_option"], json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn only_broken_json_is_an_error() {
+        assert!(parse_settings("{ \"setup_completed\": tru").is_err());
+        assert!(parse_settings("[]").is_err());
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_keys() {
+        let previous =
+            json!({ "version": 1, "setup_completed": false, "ffmpeg_custom_path": "/a" });
+        let current = json!({ "version": 2, "setup_completed": true, "gallery_paths": ["/v"] });
+        let changes = diff_config(previous.as_object().unwrap(), current.as_object().unwrap());
+
+        let keys: Vec<&str> = changes.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            ["ffmpeg_custom_path", "gallery_paths", "setup_completed"]
+        );
+        assert_eq!(changes[0].old, Some(json!("/a")));
+        assert_eq!(changes[0].new, None);
+        assert_eq!(changes[1].old, None);
+        assert_eq!(changes[2].new, Some(json!(true)));
+    }
+
+    #[test]
+    fn diff_of_identical_configs_is_empty() {
+        let config = json!({ "media": { "audio_language": "jpn" } });
+        let config = config.as_object().unwrap();
+        assert!(diff_config(config, config).is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::capabilities::{self, Component, FfmpegCapabilities};
+use crate::media_cache;
+use crate::media_toolkit::{CliToolkit, MediaToolkit};
+use crate::probe_cache::CachedToolkit;
+use std::path::Path;
+
+// Subdirectory of the cache directory holding generated thumbnails.
+const THUMBNAIL_DIR_NAME: &str = "thumbnails";
+
+// Least recently used thumbnails are deleted beyond this. At the gallery's
+// 320 px a thumbnail is 10-30 KB.
+const MAX_THUMBNAIL_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+const MIN_WIDTH: u32 = 32;
+const MAX_WIDTH: u32 = 1920;
+
+// Without an explicit time the frame comes from 1 s in, or 10% into clips
+// shorter than 10 s, which skips the black first frame most videos open with.
+const DEFAULT_TIME_SECS: f64 = 1.0;
+const DEFAULT_TIME_RATIO: f64 = 0.1;
+
+// Seeks stay this far before the end, where FFmpeg may find no frame left.
+const END_MARGIN_SECS: f64 = 0.5;
+
+// WebP when the FFmpeg build has libwebp, otherwise JPEG, which every build
+// can write.
+fn image_extension(caps: Option<&FfmpegCapabilities>) -> &'static str {
+    match caps {
+        Some(caps) if caps.has(Component::Encoder, "libwebp") => "webp",
+        _ => "jpg",
+    }
+}
+
+pub(crate) async fn thumbnail<T: MediaToolkit>(
+    toolkit: &T,
+    video_path: &str,
+    time: Option<f64>,
+    width: u32,
+) -> Result<String, String> {
+    let caps = tokio::task::spawn_blocking(capabilities::get_capabilities)
+        .await
+        .ok()
+        .and_then(Result::ok);
+    let dir = media_cache::dir(THUMBNAIL_DIR_NAME)?;
+    thumbnail_in(
+        toolkit,
+        &dir,
+        image_extension(caps.as_ref()),
+        video_path,
+        time,
+        width,
+    )
+    .await
+}
+
+// Generates (or reuses) the thumbnail inside `dir`.
+async fn thumbnail_in<T: MediaToolkit>(
+    toolkit: &T,
+    dir: &Path,
+    extension: &str,
+    video_path: &str,
+    time: Option<f64>,
+    width: u32,
+) -> Result<String, String> {
+    let width = width.clamp(MIN_WIDTH, MAX_WIDTH);
+    let duration = toolkit
+        .probe_duration(video_path)
+        .await
+        .ok()
+        .filter(|duration| *duration > 0.0);
+    let time = match (time, duration) {
+        (Some(time), Some(duration)) => time.min(duration - END_MARGIN_SECS),
+        (Some(time), None) => time,
+        (None, Some(duration)) => DEFAULT_TIME_SECS.min(duration * DEFAULT_TIME_RATIO),
+        (None, None) => 0.0,
+    }
+    .max(0.0);
+
+    // Millisecond precision keeps float noise out of the file name.
+    let millis = (time * 1000.0).round() as u64;
+    let digest = media_cache::file_digest(video_path, &format!("{}@{}", width, millis))?;
+    let target = dir.join(format!("{}.{}", digest, extension));
+
+    media_cache::get_or_create(&target, MAX_THUMBNAIL_CACHE_BYTES, |partial| async move {
+        toolkit
+            .extract_frame(video_path, millis as f64 / 1000.0, width, &partial)
+            .await
+    })
+    .await?;
+
+    Ok(target.to_string_lossy().to_string())
+}
+
+// Path of a cached still from `path` at `time` seconds (or near the start),
+// scaled to `width` pixels. FFmpeg does the decoding, so this works for files
+// the webview cannot play, and results survive restarts.
+#[tauri::command]
+pub(crate) async fn get_thumbnail(
+    path: String,
+    time: Option<f64>,
+    width: u32,
+) -> Result<String, String> {
+    thumbnail(&CachedToolkit::new(CliToolkit), &path, time, width).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_toolkit::FakeToolkit;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // A fake video and an empty thumbnail directory beside it.
+    fn video(name: &str) -> (PathBuf, String) {
+        let root = std::env::temp_dir().join(format!("thumbnails-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let file = root.join("film.mkv");
+        fs::write(&file, b"video").unwrap();
+        (root, file.to_string_lossy().to_string())
+    }
+
+    // The time and width `extract_frame` was asked for.
+    fn frame_request(toolkit: &FakeToolkit) -> (String, String) {
+        let calls = toolkit.calls();
+        let call = calls
+            .iter()
+            .find(|call| call.starts_with("extract_frame"))
+            .unwrap();
+        let args: Vec<&str> = call.split(' ').collect();
+        (args[2].to_string(), args[3].to_string())
+    }
+
+    #[tokio::test]
+    async fn width_is_clamped() {
+        let (root, path) = video("width");
+        let toolkit = FakeToolkit::new().with_duration(&path, 60.0);
+
+        thumbnail_in(
+            &toolkit,
+            &root.join("thumbs"),
+            "jpg",
+            &path,
+            Some(5.0),
+            8000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(frame_request(&toolkit).1, MAX_WIDTH.to_string());
+
+        let toolkit = FakeToolkit::new().with_duration(&path, 60.0);
+        thumbnail_in(&toolkit, &root.join("thumbs"), "jpg", &path, Some(5.0), 1)
+            .await
+            .unwrap();
+        assert_eq!(frame_request(&toolkit).1, MIN_WIDTH.to_string());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn times_past_the_end_are_pulled_back() {
+        let (root, path) = video("time");
+        let toolkit = FakeToolkit::new().with_duration(&path, 10.0);
+
+        thumbnail_in(
+            &toolkit,
+            &root.join("thumbs"),
+            "jpg",
+            &path,
+            Some(99.0),
+            320,
+        )
+        .await
+        .unwrap();
+        assert_eq!(frame_request(&toolkit).0, "9.5");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn short_clips_default_to_a_tenth_in() {
+        let (root, path) = video("default-time");
+        let toolkit = FakeToolkit::new().with_duration(&path, 4.0);
+
+        thumbnail_in(&toolkit, &root.join("thumbs"), "jpg", &path, None, 320)
+            .await
+            .unwrap();
+        assert_eq!(frame_request(&toolkit).0, "0.4");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn cached_thumbnails_keep_their_extension() {
+        let (root, path) = video("extension");
+        let toolkit = FakeToolkit::new().with_duration(&path, 60.0);
+
+        let thumb = thumbnail_in(&toolkit, &root.join("thumbs"), "webp", &path, None, 320)
+            .await
+            .unwrap();
+        assert!(thumb.ends_with(".webp"));
+        assert!(Path::new(&thumb).is_file());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn webp_only_with_libwebp() {
+        assert_eq!(image_extension(None), "jpg");
+        assert_eq!(
+            image_extension(Some(&FfmpegCapabilities::with_encoders(&["mjpeg"]))),
+            "jpg"
+        );
+        assert_eq!(
+            image_extension(Some(&FfmpegCapabilities::with_encoders(&[
+                "mjpeg", "libwebp"
+            ]))),
+            "webp"
+        );
+    }
+}
@@ -60,3 +60,22 @@ export const galleryRefreshStore = {
 	subscribe: refreshSubscribe,
 	refresh: () => refreshUpdate((n) => n + 1),
 };
+
+export interface ConfigFieldChange {
+	key: string;
+	old: unknown;
+	new: unknown;
+}
+
+// Fields of config.json changed by an edit made outside the app, as reported
+// by the backend's `config-changed` event
+const { subscribe: configChangeSubscribe, set: configChangeSet } = writable<ConfigFieldChange[]>([]);
+export const configChangeStore = {
+	subscribe: configChangeSubscribe,
+	apply: (changes: ConfigFieldChange[]) => {
+		configChangeSet(changes);
+		if (changes.some((c) => c.key === 'gallery_paths')) {
+			galleryRefreshStore.refresh();
+		}
+	},
+};
@@ -0,0 +1,21 @@
+// Shape of the error the media commands reject with. `kind` is the snake_case
+// name of the backend's FfmpegErrorKind.
+export interface FfmpegError {
+  kind: string;
+  message: string;
+  excerpt: string | null;
+}
+
+function isFfmpegError(err: unknown): err is FfmpegError {
+  return (
+    typeof err === "object" &&
+    err !== null &&
+    "kind" in err &&
+    "message" in err
+  );
+}
+
+export function formatError(err: unknown): string {
+  if (!isFfmpegError(err)) return String(err);
+  return err.excerpt ? `${err.message}\n\nDetails: ${err.excerpt}` : err.message;
+}
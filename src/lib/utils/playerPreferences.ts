@@ -24,3 +24,18 @@ export function getFadeMode(raw?: string | null): FadeMode {
 export function getFadeDurationMs(raw?: string | null): number {
   return FADE_MODE_MS[getFadeMode(raw)];
 }
+
+// Playback defaults from config.json and the folder's `.glucose.json` files,
+// as resolved by `get_effective_settings`. Unset fields fall back to the
+// player's own preferences.
+export interface MediaPreferences {
+  audio_language?: string;
+  subtitle_language?: string;
+  whisper_model?: string;
+  whisper_language?: string;
+  end_behavior?: EndBehavior;
+}
+
+export function matchesLanguage(trackLanguage: string | null, preferred?: string): boolean {
+  return !!trackLanguage && !!preferred && trackLanguage.toLowerCase() === preferred.toLowerCase();
+}
@@ -1,4 +1,4 @@
-import { convertFileSrc } from "@tauri-apps/api/core";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
 
 export const thumbnailCache = new Map<string, string>();
 const thumbnailPromises = new Map<string, Promise<string>>();
@@ -6,6 +6,7 @@ const thumbnailQueue: Array<{ run: () => void; cancel: () => void }> = [];
 let activeThumbnailJobs = 0;
 const MAX_THUMBNAIL_JOBS = 2;
 let thumbnailCacheVersion = 0;
+const THUMBNAIL_WIDTH = 320;
 
 export function scheduleThumbnailJob(job: () => Promise<string>): Promise<string> {
   return new Promise((resolve) => {
@@ -28,6 +29,29 @@ export function scheduleThumbnailJob(job: () => Promise<string>): Promise<string
   });
 }
 
+// Asks the backend to render the frame with FFmpeg, which copes with codecs
+// the webview cannot decode and keeps the result on disk across launches.
+async function createBackendThumbnail(
+  videoPath: string,
+  seekTime: number | undefined,
+  hasSeek: boolean,
+  cacheKey: string,
+  isDestroyed: () => boolean,
+  capturedVersion: number
+): Promise<string> {
+  const path = await invoke<string>('get_thumbnail', {
+    path: videoPath,
+    time: hasSeek ? seekTime : null,
+    width: THUMBNAIL_WIDTH,
+  });
+  if (isDestroyed() || thumbnailCacheVersion !== capturedVersion) {
+    return '';
+  }
+  const url = convertFileSrc(path);
+  thumbnailCache.set(cacheKey, url);
+  return url;
+}
+
 function createThumbnail(
   videoPath: string,
   seekTime: number | undefined,
@@ -63,7 +87,7 @@ function createThumbnail(
 
     function capture() {
       try {
-        const targetWidth = 320;
+        const targetWidth = THUMBNAIL_WIDTH;
         const aspectRatio = video.videoWidth / video.videoHeight;
 
         if (!Number.isFinite(aspectRatio) || aspectRatio <= 0) {
@@ -144,7 +168,10 @@ export async function generateThumbnail(
   }
 
   const capturedVersion = thumbnailCacheVersion;
-  const promise = scheduleThumbnailJob(() => createThumbnail(videoPath, seekTime, hasSeek, cacheKey, isDestroyed, capturedVersion))
+  // Falls back to capturing in the webview when FFmpeg is unavailable.
+  const promise = scheduleThumbnailJob(() =>
+    createBackendThumbnail(videoPath, seekTime, hasSeek, cacheKey, isDestroyed, capturedVersion)
+      .catch(() => createThumbnail(videoPath, seekTime, hasSeek, cacheKey, isDestroyed, capturedVersion)))
     .finally(() => thumbnailPromises.delete(cacheKey));
   
   thumbnailPromises.set(cacheKey, promise);